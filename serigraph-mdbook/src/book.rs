@@ -1,17 +1,26 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use petgraph::graph::NodeIndex;
+use petgraph::visit::{EdgeFiltered, IntoNeighborsDirected};
 use petgraph::Direction::Outgoing;
 use petgraph::Graph;
 use serde::{Deserialize, Serialize};
+use serigraph::arborescence_acyclifier::ArborescenceAcyclifier;
+use serigraph::diagram_util::write_mermaid;
+use serigraph::mask::EdgeMask;
 use serigraph::outgoing_acyclifier::OutGoingAcyclifier;
 use serigraph::Acyclifier;
 use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::process::Command;
 use std::{fmt::Display, fs::OpenOptions, io::Write, path::Path};
 
 #[derive(Debug)]
 pub struct MdBookConfig {
     pub pdf: bool,
+    /// reduce the links to a spanning arborescence so that SUMMARY.md is a tree
+    pub hierarchy: bool,
+    /// add a chapter with a Mermaid diagram of the links, cut links dashed red
+    pub diagram: bool,
 }
 
 #[derive(Debug)]
@@ -67,7 +76,7 @@ impl Book {
         })
     }
 
-    fn generate_summary(&mut self) -> Result<String> {
+    fn generate_summary(&self, mask: &EdgeMask) -> Result<String> {
         // let notes = toposort(&self.graph, None)
         //     .map_err(|e| anyhow!("{:?}", e))
         //     .map(|nis| {
@@ -84,7 +93,7 @@ impl Book {
         fn dfs(
             summary: &mut String,
             visited: &mut HashSet<NodeIndex>,
-            graph: &Graph<Note, String>,
+            graph: &EdgeFiltered<&Graph<Note, String>, &EdgeMask>,
             ni: NodeIndex,
             depth: usize,
         ) {
@@ -93,8 +102,8 @@ impl Book {
             }
             visited.insert(ni);
 
-            let note = &graph[ni];
-            let link = note.title.replace(" ", "%20");
+            let note = &graph.0[ni];
+            let link = note.title.replace(' ', "%20");
             let indent = String::from_iter(vec!['\t'; depth]);
             *summary += format!("{}- [{}](./{}.md)\n", indent, note.title, link).as_str();
 
@@ -106,15 +115,28 @@ impl Book {
         let mut summary = String::new();
         summary += "# Summary\n";
 
+        let acyclic = mask.view(&self.graph);
         for ni in self.graph.node_indices() {
-            dfs(&mut summary, &mut visited, &self.graph, ni, 0);
+            dfs(&mut summary, &mut visited, &acyclic, ni, 0);
         }
         Ok(summary)
     }
 
-    pub fn export_as_mdbook(&mut self, path: &Path, config: &MdBookConfig) -> Result<()> {
-        let acy = OutGoingAcyclifier::default();
-        acy.acyclify(&mut self.graph);
+    pub fn export_as_mdbook(&self, path: &Path, config: &MdBookConfig) -> Result<()> {
+        let feedback = if config.hierarchy {
+            let acy = ArborescenceAcyclifier::new(|_: &String| 1.0);
+            acy.feedback(&self.graph)
+        } else {
+            let acy = OutGoingAcyclifier::default();
+            acy.feedback(&self.graph)
+        };
+        let mask = EdgeMask::new(&self.graph, &feedback);
+        // cut links are kept as "see also" links on the note that made them
+        let mut see_also = HashMap::<NodeIndex, Vec<NodeIndex>>::new();
+        for edge in feedback.edges.iter() {
+            let (source, target) = self.graph.edge_endpoints(*edge).unwrap();
+            see_also.entry(target).or_default().push(source);
+        }
 
         let mut book_toml = String::new();
         book_toml += r#"
@@ -125,12 +147,19 @@ multilingual = false
 src = "src"
 title = "vault-book"
 [output.html]
-[output.katex]
+"#;
+        if config.diagram {
+            book_toml += "additional-js = [\"mermaid.min.js\", \"mermaid-init.js\"]\n";
+        }
+        book_toml += r#"[output.katex]
 [preprocessor.katex]
 "#;
         if config.pdf {
             book_toml += "[output.pdf]\n";
         }
+        if config.diagram {
+            book_toml += "[preprocessor.mermaid]\ncommand = \"mdbook-mermaid\"\n";
+        }
 
         // make dirs
         if path.exists() {
@@ -168,7 +197,17 @@ title = "vault-book"
             .truncate(true)
             .write(true)
             .open(path.join("book.toml"))?;
-        f.write(format!("{}", book_toml).as_bytes())?;
+        f.write_all(book_toml.as_bytes())?;
+        if config.diagram {
+            // mermaid.min.js and mermaid-init.js next to book.toml
+            let status = Command::new("mdbook-mermaid")
+                .arg("install")
+                .arg(path)
+                .status()?;
+            if !status.success() {
+                bail!("mdbook-mermaid install failed: {}", status);
+            }
+        }
 
         // src/*.md
         for ni in self.graph.node_indices() {
@@ -178,19 +217,40 @@ title = "vault-book"
                 .truncate(true)
                 .write(true)
                 .open(src_path.join(format!("{}.md", note.title).as_str()))?;
-            f.write(format!("{}", note.content).as_bytes())?;
+            f.write_all(note.content.as_bytes())?;
+            if let Some(sources) = see_also.get(&ni) {
+                let mut links = String::from("\n\n## See also\n");
+                for source in sources {
+                    let title = &self.graph[*source].title;
+                    links +=
+                        format!("- [{}](./{}.md)\n", title, title.replace(' ', "%20")).as_str();
+                }
+                f.write_all(links.as_bytes())?;
+            }
+        }
+
+        // src/dependencies.md
+        if config.diagram {
+            let titles = self.graph.map(|_, note| note.title.clone(), |_, _| ());
+            let mut chapter = b"# Dependencies\n\n```mermaid\n".to_vec();
+            write_mermaid(&titles, &feedback.edges, &mut chapter)?;
+            chapter.extend_from_slice(b"```\n");
+            fs::write(src_path.join("dependencies.md"), chapter)?;
         }
 
         // SUMMARY.md
         let summary_path = src_path.join("SUMMARY.md");
-        let summary = self.generate_summary()?;
+        let mut summary = self.generate_summary(&mask)?;
+        if config.diagram {
+            summary += "\n---\n\n[Dependencies](./dependencies.md)\n";
+        }
         println!("{}", summary);
         let mut f = OpenOptions::new()
             .create(true)
             .truncate(true)
             .write(true)
             .open(summary_path)?;
-        f.write(format!("{}", summary).as_bytes())?;
+        f.write_all(summary.as_bytes())?;
 
         println!("exported\n{}", self);
         Ok(())
@@ -1,4 +1,4 @@
-use std::{ops::Range, path::Path};
+use std::path::Path;
 
 use anyhow::Result;
 use book::MdBookConfig;
@@ -18,6 +18,12 @@ pub struct Args {
     #[clap(long)]
     pub pdf: bool,
 
+    #[clap(long)]
+    pub hierarchy: bool,
+
+    #[clap(long)]
+    pub diagram: bool,
+
     #[clap(long)]
     pub begin: Option<usize>,
 
@@ -30,9 +36,13 @@ fn main() -> Result<()> {
     let input_path = Path::new(&args.input_path);
     let output_path = Path::new(&args.output_path);
 
-    let mut book = Book::from_path(input_path)?;
-    let _range = args.begin.unwrap_or(0)..args.end.unwrap_or(book.graph.node_count());
-    let config = MdBookConfig { pdf: args.pdf };
+    let book = Book::from_path(input_path)?;
+    let _range = args.begin.unwrap_or(0)..args.end.unwrap_or_else(|| book.graph.node_count());
+    let config = MdBookConfig {
+        pdf: args.pdf,
+        hierarchy: args.hierarchy,
+        diagram: args.diagram,
+    };
     book.export_as_mdbook(output_path, &config)?;
     Ok(())
 }
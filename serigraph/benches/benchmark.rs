@@ -2,17 +2,24 @@ use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 
 use petgraph::graph::NodeIndex;
 use petgraph::Graph;
-use serigraph::outgoing_acyclifier::{eliminate_cycles, OutGoingAcyclifier};
+use serigraph::dfs_acyclifier::DfsAcyclifier;
+use serigraph::eades_acyclifier::EadesAcyclifier;
+use serigraph::edge_scorer::MinInDegree;
+use serigraph::locality::Clusters;
+use serigraph::metrics::{evaluate, Metrics};
+use serigraph::outgoing_acyclifier::OutGoingAcyclifier;
+use serigraph::serialize::{serialize_clustered, serialize_indexed};
+use serigraph::weighted_acyclifier::WeightedAcyclifier;
+use serigraph::Acyclifier;
 
 fn add_random_nodes_and_edges<N: Default, E: Default>(
     graph: &mut Graph<N, E>,
     n_nodes: usize,
-    density: f64,
+    n_edges: usize,
 ) {
-    for i in 0..n_nodes {
+    for _ in 0..n_nodes {
         graph.add_node(N::default());
     }
-    let n_edges = ((n_nodes * (n_nodes - 1) / 2) as f64 * density) as usize;
     for _ in 0..n_edges {
         let a = rand::random::<usize>() % n_nodes;
         let b = rand::random::<usize>() % n_nodes;
@@ -22,63 +29,148 @@ fn add_random_nodes_and_edges<N: Default, E: Default>(
     }
 }
 
-fn inspect_graph<N, E>(graph: &Graph<N, E>) {
-    for ni in graph.node_indices() {
-        let outs = graph
-            .neighbors_directed(ni, petgraph::Outgoing)
-            .map(|nni| nni.index().to_string())
-            .collect::<Vec<_>>();
-        // log::debug!("{}: {}", ni.index(), outs.join(", "));
-        println!("{}: {}", ni.index(), outs.join(", "));
-    }
-}
-
 fn outgoing_sorter_test(c: &mut Criterion) {
     let mut group = c.benchmark_group("outgoing_sorter");
     group.sample_size(10);
 
     let eliminator = OutGoingAcyclifier::default();
-    // for n_nodes in [10, 50, 100] {
-    for n_nodes in [100, 200, 300, 400] {
-        group.throughput(criterion::Throughput::Elements(n_nodes));
+    for n_nodes in [100, 200, 300, 400, 500] {
+        group.throughput(criterion::Throughput::Elements(n_nodes as u64));
         group.bench_with_input(
             BenchmarkId::from_parameter(n_nodes),
             &n_nodes,
             |b, &n_nodes| {
+                // density 0.2
+                let n_edges = n_nodes * (n_nodes - 1) / 10;
                 let mut graph = Graph::<i64, i64>::new();
-                add_random_nodes_and_edges(&mut graph, (n_nodes as u64).try_into().unwrap(), 0.3);
-                b.iter(|| {
-                    eliminator.eliminate_cycles(&mut graph);
-                });
+                add_random_nodes_and_edges(&mut graph, n_nodes, n_edges);
+                b.iter(|| eliminator.feedback(&graph));
             },
         );
     }
     group.finish();
 }
 
-fn serialize_test(c: &mut Criterion) {
-    let mut group = c.benchmark_group("serialize");
+fn sparse_test(c: &mut Criterion) {
+    let mut group = c.benchmark_group("outgoing_sorter_sparse");
     group.sample_size(10);
 
     let eliminator = OutGoingAcyclifier::default();
-    // for n_nodes in [10, 50, 100] {
-    // for n_nodes in [100, 200, 300, 400] {
-    for n_nodes in [500] {
-        group.throughput(criterion::Throughput::Elements(n_nodes));
+    for n_nodes in [10_000, 50_000] {
+        group.throughput(criterion::Throughput::Elements(n_nodes as u64));
         group.bench_with_input(
             BenchmarkId::from_parameter(n_nodes),
             &n_nodes,
             |b, &n_nodes| {
+                // three links per note on average
                 let mut graph = Graph::<i64, i64>::new();
-                add_random_nodes_and_edges(&mut graph, (n_nodes as u64).try_into().unwrap(), 0.2);
-                b.iter(|| {
-                    eliminate_cycles(&mut graph, Some(100));
-                });
+                add_random_nodes_and_edges(&mut graph, n_nodes, n_nodes * 3);
+                b.iter(|| eliminator.feedback(&graph));
             },
         );
     }
     group.finish();
 }
 
-criterion_group!(benches, serialize_test,);
+type Strategy = (&'static str, Box<dyn Acyclifier<i64, i64>>);
+
+fn strategies_test(c: &mut Criterion) {
+    let mut group = c.benchmark_group("strategies");
+    group.sample_size(10);
+
+    let strategies: Vec<Strategy> = vec![
+        ("outgoing", Box::new(OutGoingAcyclifier::default())),
+        (
+            "outgoing_min_in",
+            Box::new(OutGoingAcyclifier::new(MinInDegree)),
+        ),
+        ("eades", Box::new(EadesAcyclifier)),
+        ("dfs", Box::new(DfsAcyclifier::default())),
+        ("weighted", Box::new(WeightedAcyclifier::new(|_: &i64| 1.0))),
+    ];
+    // the same graph for every strategy, so that their metrics compare
+    let mut graph = Graph::<i64, i64>::new();
+    add_random_nodes_and_edges(&mut graph, 1000, 3000);
+    for (name, acyclifier) in strategies.iter() {
+        let metrics = evaluate(&graph, &acyclifier.as_ref(), |_| 1.0).unwrap();
+        println!(
+            "{}: backward {}, removed {:.3}, forward distance {:.1}, locality {:.3}",
+            name,
+            metrics.backward,
+            metrics.removed_fraction,
+            metrics.mean_forward_distance,
+            metrics.mean_locality()
+        );
+        group.bench_function(*name, |b| b.iter(|| acyclifier.feedback(&graph)));
+    }
+    group.finish();
+}
+
+fn locality_test(c: &mut Criterion) {
+    let mut group = c.benchmark_group("locality");
+    group.sample_size(10);
+
+    // 50 tightly linked clusters of 20 notes, node i in cluster i % 50
+    let (n_clusters, n_nodes) = (50, 1000);
+    let mut graph = Graph::<i64, i64>::new();
+    for _ in 0..n_nodes {
+        graph.add_node(0);
+    }
+    for _ in 0..n_nodes * 3 {
+        let cluster = rand::random::<usize>() % n_clusters;
+        let a = cluster + n_clusters * (rand::random::<usize>() % (n_nodes / n_clusters));
+        let b = cluster + n_clusters * (rand::random::<usize>() % (n_nodes / n_clusters));
+        if a != b {
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), 0);
+        }
+    }
+    for _ in 0..n_nodes / 5 {
+        let a = rand::random::<usize>() % n_nodes;
+        let b = rand::random::<usize>() % n_nodes;
+        if a != b {
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), 0);
+        }
+    }
+
+    let acyclifier = OutGoingAcyclifier::default();
+    let feedback = acyclifier.feedback(&graph);
+    let report = |name: &str, order: Vec<NodeIndex>| {
+        let metrics = Metrics::new(&graph, &order, &feedback, |_| 1.0);
+        println!(
+            "{}: backward {}, forward distance {:.1}, locality {:.3}",
+            name,
+            metrics.backward,
+            metrics.mean_forward_distance,
+            metrics.mean_locality()
+        );
+    };
+    let indices = |nodes: Vec<(NodeIndex, i64)>| nodes.into_iter().map(|(ni, _)| ni).collect();
+    report(
+        "index",
+        indices(serialize_indexed(&graph, &acyclifier).unwrap()),
+    );
+    for (name, clusters) in [
+        ("sccs", Clusters::Sccs),
+        ("communities", Clusters::Communities),
+    ] {
+        let order = serialize_clustered(&graph, &acyclifier, &clusters).unwrap();
+        report(name, indices(order));
+    }
+
+    group.bench_function("index", |b| {
+        b.iter(|| serialize_indexed(&graph, &acyclifier))
+    });
+    group.bench_function("communities", |b| {
+        b.iter(|| serialize_clustered(&graph, &acyclifier, &Clusters::Communities))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    outgoing_sorter_test,
+    sparse_test,
+    strategies_test,
+    locality_test
+);
 criterion_main!(benches);
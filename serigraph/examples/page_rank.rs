@@ -1,20 +1,24 @@
-use petgraph::graph::NodeIndex;
-use petgraph::visit::EdgeRef;
+use petgraph::Direction::Incoming;
 use petgraph::Graph;
-use simple_pagerank::Pagerank;
-
-fn with_page_rank(graph: &)
+use serigraph::eades_acyclifier::EadesAcyclifier;
+use serigraph::rank::PageRank;
+use serigraph::serialize::serialize_ranked;
 
 fn main() {
-    let edges = &[(0, 1), (1, 2), (1, 3), (2, 3)];
-    let mut graph = Graph::<usize, usize>::from_edges(edges);
+    let edges = &[(0, 1), (1, 2), (1, 3), (2, 3), (3, 1)];
+    let graph = Graph::<usize, usize>::from_edges(edges);
 
-    let mut pr = Pagerank::<NodeIndex>::new();
-    for e in graph.edge_references() {
-        pr.add_edge(e.source(), e.target());
-    }
-    pr.calculate();
-    for (score, index) in pr.nodes() {
+    let page_rank = PageRank {
+        direction: Incoming,
+        ..PageRank::default()
+    };
+    for (index, score) in page_rank.rank(&graph).iter().enumerate() {
         println!("{:?}: {:?}", index, score);
     }
+
+    let order = serialize_ranked(&graph, &EadesAcyclifier, &page_rank).unwrap();
+    println!(
+        "{:?}",
+        order.iter().map(|(ni, _)| ni.index()).collect::<Vec<_>>()
+    );
 }
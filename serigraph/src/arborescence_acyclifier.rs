@@ -0,0 +1,307 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+
+use crate::mask::{Feedback, Status};
+use crate::serialize::{check_relabeled, relabel, SerializeError};
+use crate::Acyclifier;
+
+/// reduces the graph to a minimum (or maximum) spanning arborescence by Chu–Liu/Edmonds
+///
+/// Without a `root`, roots are chosen so that as few nodes as possible become roots.
+/// Nodes unreachable from the given `root` become roots of their own trees.
+pub struct ArborescenceAcyclifier<F> {
+    pub cost: F,
+    pub root: Option<NodeIndex>,
+    pub maximum: bool,
+}
+
+impl<F> ArborescenceAcyclifier<F> {
+    pub fn new(cost: F) -> Self {
+        ArborescenceAcyclifier {
+            cost,
+            root: None,
+            maximum: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arborescence {
+    pub roots: Vec<NodeIndex>,
+    pub edges: Vec<EdgeIndex>,
+    /// total cost of `edges`
+    pub weight: f64,
+}
+
+/// indices of the edges of a minimum arborescence of `0..n` rooted at `root`
+///
+/// Every node other than `root` must have an incoming edge.
+pub fn min_arborescence(n: usize, edges: &[(usize, usize, f64)], root: usize) -> Vec<usize> {
+    let mut min_in: Vec<Option<usize>> = vec![None; n];
+    for (i, (s, t, w)) in edges.iter().enumerate() {
+        if s == t || *t == root {
+            continue;
+        }
+        match min_in[*t] {
+            Some(j) if edges[j].2 <= *w => {}
+            _ => min_in[*t] = Some(i),
+        }
+    }
+
+    // group the nodes, each cycle of minimum incoming edges becomes one group
+    let mut group = vec![usize::MAX; n];
+    let mut in_cycle = vec![false; n];
+    let mut visited_from = vec![usize::MAX; n];
+    let mut n_groups = 0;
+    for v in 0..n {
+        let mut curr = v;
+        while visited_from[curr] == usize::MAX && curr != root {
+            visited_from[curr] = v;
+            curr = edges[min_in[curr].expect("every node must be reachable")].0;
+        }
+        if curr != root && visited_from[curr] == v && group[curr] == usize::MAX {
+            let start = curr;
+            loop {
+                group[curr] = n_groups;
+                in_cycle[curr] = true;
+                curr = edges[min_in[curr].unwrap()].0;
+                if curr == start {
+                    break;
+                }
+            }
+            n_groups += 1;
+        }
+    }
+    if n_groups == 0 {
+        return min_in.into_iter().flatten().collect();
+    }
+    for g in group.iter_mut().filter(|g| **g == usize::MAX) {
+        *g = n_groups;
+        n_groups += 1;
+    }
+
+    let mut contracted = vec![];
+    let mut origins = vec![];
+    for (i, (s, t, w)) in edges.iter().enumerate() {
+        if group[*s] == group[*t] {
+            continue;
+        }
+        let w = if in_cycle[*t] {
+            w - edges[min_in[*t].unwrap()].2
+        } else {
+            *w
+        };
+        contracted.push((group[*s], group[*t], w));
+        origins.push(i);
+    }
+
+    let mut entered = vec![false; n];
+    let mut chosen = vec![];
+    for j in min_arborescence(n_groups, &contracted, group[root]) {
+        let i = origins[j];
+        entered[edges[i].1] = true;
+        chosen.push(i);
+    }
+    // keep every cycle edge except the one into the node where the cycle is entered
+    for v in 0..n {
+        if in_cycle[v] && !entered[v] {
+            chosen.push(min_in[v].unwrap());
+        }
+    }
+    chosen
+}
+
+impl<F> ArborescenceAcyclifier<F> {
+    pub fn arborescence<N, E>(&self, graph: &Graph<N, E>) -> Arborescence
+    where
+        F: Fn(&E) -> f64,
+    {
+        self.arborescence_from(graph, self.root)
+    }
+
+    /// `arborescence` with `root` in place of `self.root`
+    fn arborescence_from<N, E>(&self, graph: &Graph<N, E>, root: Option<NodeIndex>) -> Arborescence
+    where
+        F: Fn(&E) -> f64,
+    {
+        let n = graph.node_count();
+        let sign = if self.maximum { -1.0 } else { 1.0 };
+        let mut edges = graph
+            .edge_references()
+            .map(|e| {
+                let w = sign * (self.cost)(e.weight());
+                (e.source().index(), e.target().index(), w)
+            })
+            .collect::<Vec<_>>();
+        // the given root is only entered from the virtual root, edges into it become self
+        // loops, which are never chosen, so that the other edges keep their indices
+        if let Some(root) = root {
+            for edge in edges.iter_mut().filter(|(_, t, _)| *t == root.index()) {
+                edge.0 = edge.1;
+            }
+        }
+
+        // a virtual root at `n` reaches every node, its edges cost more than any real tree
+        // so that they are only used for nodes which cannot be reached otherwise
+        let penalty = edges.iter().map(|(_, _, w)| w.abs()).sum::<f64>() + 1.0;
+        for v in 0..n {
+            let w = match root {
+                Some(root) if root.index() == v => 0.0,
+                _ => penalty,
+            };
+            edges.push((n, v, w));
+        }
+
+        let n_edges = graph.edge_count();
+        let mut arborescence = Arborescence {
+            roots: vec![],
+            edges: vec![],
+            weight: 0.0,
+        };
+        for i in min_arborescence(n + 1, &edges, n) {
+            if i < n_edges {
+                arborescence.edges.push(EdgeIndex::new(i));
+                arborescence.weight += sign * edges[i].2;
+            } else {
+                arborescence.roots.push(NodeIndex::new(edges[i].1));
+            }
+        }
+        arborescence.roots.sort();
+        arborescence.edges.sort();
+        arborescence
+    }
+}
+
+impl<F> ArborescenceAcyclifier<F> {
+    /// every edge outside the arborescence from `root`
+    fn feedback_from<N, E>(&self, graph: &Graph<N, E>, root: Option<NodeIndex>) -> Feedback
+    where
+        F: Fn(&E) -> f64,
+    {
+        let mut keep = vec![false; graph.edge_count()];
+        for e in self.arborescence_from(graph, root).edges {
+            keep[e.index()] = true;
+        }
+        Feedback {
+            edges: graph.edge_indices().filter(|e| !keep[e.index()]).collect(),
+            optimal: vec![],
+            iterations: 1,
+            status: Status::Complete,
+        }
+    }
+}
+
+impl<N, E, F: Fn(&E) -> f64> Acyclifier<N, E> for ArborescenceAcyclifier<F> {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        self.feedback_from(graph, self.root)
+    }
+
+    fn validate_relabeled(
+        &self,
+        graph: &Graph<N, E>,
+        ranks: &[usize],
+    ) -> Result<(), SerializeError> {
+        check_relabeled(graph, ranks, &self.root)
+    }
+
+    fn feedback_relabeled(&self, graph: &Graph<N, E>, ranks: &[usize]) -> Feedback {
+        let root = self.root.and_then(|ni| relabel(graph, ranks, ni));
+        self.feedback_from(graph, root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::{EdgeIndex, NodeIndex};
+    use petgraph::visit::EdgeRef;
+    use petgraph::Direction::Incoming;
+    use petgraph::Graph;
+
+    use super::{min_arborescence, ArborescenceAcyclifier};
+    use crate::Acyclifier;
+
+    /// same graph as `verify/chu_liu.py`: [r: 0, a: 1, b: 2, c: 3, d: 4, e: 5]
+    fn edges() -> Vec<(u32, u32, f64)> {
+        vec![
+            (0, 3, 8.0),
+            (0, 1, 2.0),
+            (0, 2, 10.0),
+            (1, 3, 2.0),
+            (2, 1, 1.0),
+            (2, 4, 1.0),
+            (3, 2, 8.0),
+            (3, 4, 3.0),
+            (4, 5, 1.0),
+            (5, 2, 2.0),
+        ]
+    }
+
+    #[test]
+    fn test_min_arborescence() {
+        let edges = edges()
+            .into_iter()
+            .map(|(s, t, w)| (s as usize, t as usize, w))
+            .collect::<Vec<_>>();
+        let mut chosen = min_arborescence(6, &edges, 0);
+        chosen.sort();
+        // r -> a -> c -> d -> e -> b
+        assert_eq!(chosen, vec![1, 3, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_arborescence() {
+        let graph = Graph::<(), f64>::from_edges(edges());
+        let acy = ArborescenceAcyclifier::new(|w: &f64| *w);
+        let tree = acy.arborescence(&graph);
+        assert_eq!(tree.roots, vec![NodeIndex::new(0)]);
+        assert_eq!(tree.weight, 10.0);
+
+        let acy = ArborescenceAcyclifier {
+            maximum: true,
+            ..ArborescenceAcyclifier::new(|w: &f64| *w)
+        };
+        let tree = acy.arborescence(&graph);
+        // r -> a, r -> b, r -> c, c -> d, d -> e
+        assert_eq!(tree.weight, 24.0);
+    }
+
+    #[test]
+    fn test_acyclify() {
+        // 0 <-> 1 and an unreachable 2 -> 3
+        let mut graph =
+            Graph::<i32, f64>::from_edges([(0, 1, 1.0), (1, 0, 1.0), (2, 3, 1.0), (1, 3, 5.0)]);
+        let acy = ArborescenceAcyclifier {
+            root: Some(NodeIndex::new(1)),
+            ..ArborescenceAcyclifier::new(|w: &f64| *w)
+        };
+        acy.acyclify(&mut graph);
+        let mut edges = graph
+            .edge_references()
+            .map(|e| (e.source().index(), e.target().index()))
+            .collect::<Vec<_>>();
+        edges.sort();
+        assert_eq!(edges, vec![(1, 0), (2, 3)]);
+        assert!(graph
+            .node_indices()
+            .all(|ni| graph.neighbors_directed(ni, Incoming).count() <= 1));
+    }
+
+    #[test]
+    fn test_maximum_with_root() {
+        // taking 1 -> 0 would gain weight, but 0 stays a root and 1 becomes another
+        let graph = Graph::<i32, f64>::from_edges([(1, 0, 10.0)]);
+        let acy = ArborescenceAcyclifier {
+            root: Some(NodeIndex::new(0)),
+            maximum: true,
+            ..ArborescenceAcyclifier::new(|w: &f64| *w)
+        };
+        let arborescence = acy.arborescence(&graph);
+        assert_eq!(
+            arborescence.roots,
+            vec![NodeIndex::new(0), NodeIndex::new(1)]
+        );
+        assert_eq!(arborescence.edges, vec![]);
+        assert_eq!(acy.feedback(&graph).edges, vec![EdgeIndex::new(0)]);
+    }
+}
@@ -0,0 +1,243 @@
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction::Outgoing;
+use petgraph::Graph;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::mask::{EdgeMask, Feedback, Status};
+use crate::serialize::{check_nodes, SerializeError};
+use crate::Acyclifier;
+
+/// hard ordering rules that hold whatever the links say
+///
+/// A link that agrees with a rule is never cut, links against a rule always are.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Constraints {
+    /// nodes that come before every other node, in this order
+    pub first: Vec<NodeIndex>,
+    /// nodes that come after every other node, in this order
+    pub last: Vec<NodeIndex>,
+    /// pairs `(a, b)` where `a` comes before `b`
+    pub precedes: Vec<(NodeIndex, NodeIndex)>,
+}
+
+impl Constraints {
+    /// the rules as a graph on the nodes of a graph with `node_count` nodes
+    fn arcs(&self, node_count: usize) -> Graph<(), ()> {
+        let mut arcs = Graph::with_capacity(node_count, 0);
+        for _ in 0..node_count {
+            arcs.add_node(());
+        }
+        for pinned in [&self.first, &self.last] {
+            for pair in pinned.windows(2) {
+                arcs.add_edge(pair[0], pair[1], ());
+            }
+        }
+        let mut pinned = vec![false; node_count];
+        for ni in self.first.iter().chain(self.last.iter()) {
+            pinned[ni.index()] = true;
+        }
+        let middle = (0..node_count)
+            .filter(|i| !pinned[*i])
+            .map(NodeIndex::new)
+            .collect::<Vec<_>>();
+        if let Some(first) = self.first.last() {
+            for ni in middle.iter() {
+                arcs.add_edge(*first, *ni, ());
+            }
+        }
+        if let Some(last) = self.last.first() {
+            for ni in middle.iter() {
+                arcs.add_edge(*ni, *last, ());
+            }
+        }
+        if let (true, Some(first), Some(last)) =
+            (middle.is_empty(), self.first.last(), self.last.first())
+        {
+            arcs.add_edge(*first, *last, ());
+        }
+        for (a, b) in self.precedes.iter() {
+            arcs.add_edge(*a, *b, ());
+        }
+        arcs
+    }
+
+    /// nodes of `graph` in reading order and the links cut to get it
+    ///
+    /// Ties go to the smaller node index. Links are first acyclified by `acyclifier`,
+    /// whatever cycles the rules still close are broken by cutting links into the next node.
+    /// The rules themselves forming a cycle is a `SerializeError::Constraint`, a rule naming
+    /// a node that is not in `graph` is a `SerializeError::UnknownNode`.
+    pub fn order<N, E, A>(
+        &self,
+        graph: &Graph<N, E>,
+        acyclifier: &A,
+    ) -> Result<(Vec<NodeIndex>, Feedback), SerializeError>
+    where
+        A: Acyclifier<N, E>,
+    {
+        check_nodes(
+            graph,
+            self.first
+                .iter()
+                .chain(self.last.iter())
+                .chain(self.precedes.iter().flat_map(|(a, b)| [a, b])),
+        )?;
+        let n = graph.node_count();
+        let arcs = self.arcs(n);
+        let cycle = tarjan_scc(&arcs)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || arcs.contains_edge(scc[0], scc[0]))
+            .map(|mut scc| {
+                scc.sort();
+                scc
+            })
+            .min();
+        if let Some(cycle) = cycle {
+            return Err(SerializeError::Constraint(cycle));
+        }
+
+        acyclifier.validate(graph)?;
+        let feedback = acyclifier.feedback(graph);
+        let mask = EdgeMask::new(graph, &feedback);
+        let mut link_in = vec![0usize; n];
+        for e in graph.edge_references().filter(|e| mask.contains(e.id())) {
+            link_in[e.target().index()] += 1;
+        }
+        let mut arc_in = vec![0usize; n];
+        for e in arcs.edge_references() {
+            arc_in[e.target().index()] += 1;
+        }
+        let mut ready = graph
+            .node_indices()
+            .filter(|ni| link_in[ni.index()] == 0 && arc_in[ni.index()] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+
+        let mut emitted = vec![false; n];
+        let mut order = Vec::with_capacity(n);
+        while order.len() < n {
+            while let Some(Reverse(ni)) = ready.pop() {
+                emitted[ni.index()] = true;
+                order.push(ni);
+                for e in graph.edges_directed(ni, Outgoing) {
+                    let next = e.target().index();
+                    if mask.contains(e.id()) && !emitted[next] {
+                        link_in[next] -= 1;
+                        if link_in[next] == 0 && arc_in[next] == 0 {
+                            ready.push(Reverse(e.target()));
+                        }
+                    }
+                }
+                for next in arcs.neighbors_directed(ni, Outgoing) {
+                    arc_in[next.index()] -= 1;
+                    if link_in[next.index()] == 0 && arc_in[next.index()] == 0 {
+                        ready.push(Reverse(next));
+                    }
+                }
+            }
+            // links close a cycle against the rules, the rules are acyclic so some node is free
+            if let Some(ni) = graph
+                .node_indices()
+                .find(|ni| !emitted[ni.index()] && arc_in[ni.index()] == 0)
+            {
+                link_in[ni.index()] = 0;
+                ready.push(Reverse(ni));
+            }
+        }
+
+        let mut positions = vec![0; n];
+        for (i, ni) in order.iter().enumerate() {
+            positions[ni.index()] = i;
+        }
+        let edges = graph
+            .edge_references()
+            .filter(|e| positions[e.source().index()] >= positions[e.target().index()])
+            .map(|e| e.id())
+            .collect();
+        let feedback = Feedback {
+            edges,
+            optimal: vec![],
+            iterations: feedback.iterations,
+            status: Status::Complete,
+        };
+        Ok((order, feedback))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::{EdgeIndex, NodeIndex};
+    use petgraph::Graph;
+
+    use super::Constraints;
+    use crate::eades_acyclifier::EadesAcyclifier;
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+    use crate::serialize::SerializeError;
+    use crate::Acyclifier;
+
+    fn to_nis(indices: &[usize]) -> Vec<NodeIndex> {
+        indices.iter().map(|i| NodeIndex::new(*i)).collect()
+    }
+
+    #[test]
+    fn test_constraints() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 2), (2, 0), (1, 3), (4, 1)]);
+        let constraints = Constraints {
+            first: to_nis(&[3]),
+            last: to_nis(&[0]),
+            precedes: vec![(NodeIndex::new(2), NodeIndex::new(1))],
+        };
+        let (order, feedback) = constraints
+            .order(&graph, &OutGoingAcyclifier::default())
+            .unwrap();
+        assert_eq!(order, to_nis(&[3, 4, 2, 1, 0]));
+        // 2 -> 0 agrees with 0 being last, so it stays
+        assert_eq!(feedback.edges, [0, 1, 3].map(EdgeIndex::new));
+    }
+
+    #[test]
+    fn test_protected_link() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 0)]);
+        assert_eq!(
+            EadesAcyclifier.feedback(&graph).edges,
+            vec![EdgeIndex::new(1)]
+        );
+        let constraints = Constraints {
+            precedes: vec![(NodeIndex::new(1), NodeIndex::new(0))],
+            ..Constraints::default()
+        };
+        let (order, feedback) = constraints.order(&graph, &EadesAcyclifier).unwrap();
+        assert_eq!(order, to_nis(&[1, 0]));
+        assert_eq!(feedback.edges, vec![EdgeIndex::new(0)]);
+    }
+
+    #[test]
+    fn test_constraint_cycle() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (2, 3)]);
+        let constraints = Constraints {
+            first: to_nis(&[2]),
+            precedes: vec![(NodeIndex::new(1), NodeIndex::new(2))],
+            ..Constraints::default()
+        };
+        assert_eq!(
+            constraints.order(&graph, &EadesAcyclifier),
+            Err(SerializeError::Constraint(to_nis(&[1, 2])))
+        );
+    }
+
+    #[test]
+    fn test_unknown_node() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (2, 3)]);
+        let constraints = Constraints {
+            precedes: vec![(NodeIndex::new(1), NodeIndex::new(7))],
+            ..Constraints::default()
+        };
+        assert_eq!(
+            constraints.order(&graph, &EadesAcyclifier),
+            Err(SerializeError::UnknownNode(NodeIndex::new(7)))
+        );
+    }
+}
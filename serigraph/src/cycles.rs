@@ -0,0 +1,215 @@
+use anyhow::Result;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::Graph;
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::dot_util::dump_cycles;
+
+/// caps on the search of `elementary_cycles`, `None` is unbounded
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CycleLimits {
+    /// stop after this many cycles
+    pub max_cycles: Option<usize>,
+    /// skip cycles through more nodes than this
+    pub max_len: Option<usize>,
+}
+
+/// elementary cycles of a graph
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Cycles {
+    /// every cycle starts at its smallest node index, the last node links back to the first
+    pub cycles: Vec<Vec<NodeIndex>>,
+    /// a limit stopped the search, so there may be more cycles
+    pub truncated: bool,
+}
+
+impl Cycles {
+    /// write every cycle into one DOT file at `path` with `dump_cycles`
+    pub fn dump<N: Display, E: Display>(&self, graph: &Graph<N, E>, path: &Path) -> Result<()> {
+        dump_cycles(graph, &self.cycles, path)
+    }
+}
+
+struct Frame {
+    node: NodeIndex,
+    next: Vec<NodeIndex>,
+    position: usize,
+    found: bool,
+}
+
+fn unblock(ni: NodeIndex, blocked: &mut [bool], blockers: &mut [Vec<NodeIndex>]) {
+    let mut stack = vec![ni];
+    while let Some(ni) = stack.pop() {
+        if blocked[ni.index()] {
+            blocked[ni.index()] = false;
+            stack.append(&mut blockers[ni.index()]);
+        }
+    }
+}
+
+/// every elementary cycle of `graph` by Johnson's algorithm
+///
+/// Cycles are found from the smallest start node up, parallel edges give a cycle once.
+pub fn elementary_cycles<N, E>(graph: &Graph<N, E>, limits: &CycleLimits) -> Cycles {
+    let n = graph.node_count();
+    let mut scc_of = vec![0; n];
+    for (i, scc) in tarjan_scc(graph).into_iter().enumerate() {
+        for ni in scc {
+            scc_of[ni.index()] = i;
+        }
+    }
+
+    let mut found = Cycles::default();
+    let mut blocked = vec![false; n];
+    let mut blockers = vec![vec![]; n];
+    let mut touched = vec![];
+    'starts: for start in graph.node_indices() {
+        // a cycle starting at `start` stays in its component and above its index
+        let next = |ni: NodeIndex| {
+            let mut next = graph
+                .neighbors(ni)
+                .filter(|w| *w >= start && scc_of[w.index()] == scc_of[start.index()])
+                .collect::<Vec<_>>();
+            next.sort();
+            next.dedup();
+            next
+        };
+
+        let mut path = vec![start];
+        blocked[start.index()] = true;
+        touched.push(start);
+        let mut stack = vec![Frame {
+            node: start,
+            next: next(start),
+            position: 0,
+            found: false,
+        }];
+        while let Some(frame) = stack.last_mut() {
+            if frame.position < frame.next.len() {
+                let w = frame.next[frame.position];
+                frame.position += 1;
+                if w == start {
+                    if limits.max_cycles == Some(found.cycles.len()) {
+                        found.truncated = true;
+                        break 'starts;
+                    }
+                    found.cycles.push(path.clone());
+                    frame.found = true;
+                } else if !blocked[w.index()] {
+                    if matches!(limits.max_len, Some(len) if path.len() >= len) {
+                        // treat the cut path as a cycle so that its nodes are not left blocked
+                        frame.found = true;
+                        found.truncated = true;
+                        continue;
+                    }
+                    blocked[w.index()] = true;
+                    touched.push(w);
+                    path.push(w);
+                    stack.push(Frame {
+                        node: w,
+                        next: next(w),
+                        position: 0,
+                        found: false,
+                    });
+                }
+            } else {
+                let frame = stack.pop().unwrap();
+                path.pop();
+                if frame.found {
+                    unblock(frame.node, &mut blocked, &mut blockers);
+                } else {
+                    for w in frame.next {
+                        if !blockers[w.index()].contains(&frame.node) {
+                            blockers[w.index()].push(frame.node);
+                        }
+                    }
+                }
+                if let Some(parent) = stack.last_mut() {
+                    parent.found |= frame.found;
+                }
+            }
+        }
+        for ni in touched.drain(..) {
+            blocked[ni.index()] = false;
+            blockers[ni.index()].clear();
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+    use std::fs;
+
+    use super::{elementary_cycles, CycleLimits};
+
+    fn to_nis(cycles: &[&[usize]]) -> Vec<Vec<NodeIndex>> {
+        cycles
+            .iter()
+            .map(|c| c.iter().map(|i| NodeIndex::new(*i)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_elementary_cycles() {
+        let graph = Graph::<i32, i32>::from_edges([
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (1, 3),
+            (3, 2),
+            (3, 3),
+            (2, 0),
+            (4, 0),
+        ]);
+        let all = elementary_cycles(&graph, &CycleLimits::default());
+        assert_eq!(all.cycles, to_nis(&[&[0, 1, 2], &[0, 1, 3, 2], &[3]]));
+        assert!(!all.truncated);
+
+        let short = elementary_cycles(
+            &graph,
+            &CycleLimits {
+                max_len: Some(3),
+                ..CycleLimits::default()
+            },
+        );
+        assert_eq!(short.cycles, to_nis(&[&[0, 1, 2], &[3]]));
+        assert!(short.truncated);
+
+        let first = elementary_cycles(
+            &graph,
+            &CycleLimits {
+                max_cycles: Some(1),
+                ..CycleLimits::default()
+            },
+        );
+        assert_eq!(first.cycles, to_nis(&[&[0, 1, 2]]));
+        assert!(first.truncated);
+
+        let path = std::env::temp_dir().join("serigraph_test_cycles.dot");
+        all.dump(&graph, &path).unwrap();
+        let dot = fs::read_to_string(&path).unwrap();
+        assert_eq!(dot.matches("subgraph").count(), 3);
+        assert_eq!(dot.matches("->").count(), 3 + 4 + 1);
+    }
+
+    #[test]
+    fn test_complete_graph() {
+        // a complete digraph on n nodes has sum over k of C(n, k) (k - 1)! elementary cycles
+        let mut graph = Graph::<(), ()>::new();
+        let nis = (0..5).map(|_| graph.add_node(())).collect::<Vec<_>>();
+        for a in &nis {
+            for b in &nis {
+                if a != b {
+                    graph.add_edge(*a, *b, ());
+                }
+            }
+        }
+        let cycles = elementary_cycles(&graph, &CycleLimits::default());
+        assert_eq!(cycles.cycles.len(), 10 + 20 + 30 + 24);
+    }
+}
@@ -1,39 +1,134 @@
-use std::fmt::Display;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction::Outgoing;
+use petgraph::Graph;
 
+use crate::mask::{Feedback, Status};
+use crate::rank::PageRank;
+use crate::serialize::{check_relabeled, relabel, SerializeError};
 use crate::Acyclifier;
 
+/// order in which the DFS picks its roots
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum RootSelection {
+    /// nodes with the most outgoing edges first, ties by insertion order
+    #[default]
+    MaxOutDegree,
+    /// nodes in insertion order
+    InsertionOrder,
+    /// the given nodes first, then the rest in insertion order, nodes not in the graph are
+    /// skipped
+    Given(Vec<NodeIndex>),
+    /// nodes with the highest PageRank first, so that edges into central nodes get cut
+    PageRank(PageRank),
+}
+
+/// removes the back edges found by a depth-first search
 #[derive(Default, Debug)]
-pub struct DfsAcyclifier;
-
-impl<N: Display + Clone, E> Acyclifier<N, E> for DfsAcyclifier {
-    fn acyclify(&self, graph: &mut petgraph::Graph<N, E>) {
-        todo!()
-        // let mut nodes_by_ref_count = graph.node_indices().collect::<Vec<_>>();
-        // nodes_by_ref_count.sort_by_key(|n| {
-        //     graph
-        //         .neighbors_directed(*n, petgraph::Direction::Outgoing)
-        //         .count()
-        // });
-        // nodes_by_ref_count.reverse();
-
-        // let mut res: Vec<N> = vec![];
-        // let mut flags: HashSet<NodeIndex> = HashSet::new();
-        // for ni in nodes_by_ref_count {
-        //     let mut q: VecDeque<NodeIndex> = VecDeque::new();
-        //     q.push_back(ni);
-        //     while let Some(ni) = q.pop_front() {
-        //         for n in graph.neighbors_directed(ni, Outgoing) {
-        //             if !flags.contains(&ni) {
-        //                 q.push_back(n);
-        //             }
-        //         }
-        //         if !flags.contains(&ni) {
-        //             res.push(graph[ni].clone());
-        //             flags.insert(ni);
-        //         }
-        //     }
-        //     println!("")
-        // }
+pub struct DfsAcyclifier {
+    pub roots: RootSelection,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Unvisited,
+    OnStack,
+    Done,
+}
+
+impl DfsAcyclifier {
+    fn root_order<N, E>(&self, graph: &Graph<N, E>) -> Vec<NodeIndex> {
+        let mut roots = graph.node_indices().collect::<Vec<_>>();
+        match &self.roots {
+            RootSelection::MaxOutDegree => {
+                roots.sort_by_key(|ni| {
+                    std::cmp::Reverse(graph.neighbors_directed(*ni, Outgoing).count())
+                });
+            }
+            RootSelection::InsertionOrder => {}
+            RootSelection::Given(given) => {
+                roots = given
+                    .iter()
+                    .cloned()
+                    .filter(|ni| ni.index() < graph.node_count())
+                    .chain(roots)
+                    .collect();
+            }
+            RootSelection::PageRank(page_rank) => {
+                roots = page_rank.order(graph);
+            }
+        }
+        roots
+    }
+
+    /// the same search on a relabeled graph, whose node `ni` is `to(ni)`, given roots that
+    /// map to no node are dropped
+    pub(crate) fn relabeled(&self, to: impl Fn(NodeIndex) -> Option<NodeIndex>) -> Self {
+        let roots = match &self.roots {
+            RootSelection::Given(given) => {
+                RootSelection::Given(given.iter().filter_map(|ni| to(*ni)).collect())
+            }
+            roots => roots.clone(),
+        };
+        DfsAcyclifier { roots }
+    }
+
+    /// edges pointing back to a node on the DFS stack
+    pub fn back_edges<N, E>(&self, graph: &Graph<N, E>) -> Vec<EdgeIndex> {
+        let mut marks = vec![Mark::Unvisited; graph.node_count()];
+        let mut back_edges = vec![];
+
+        for root in self.root_order(graph) {
+            if marks[root.index()] != Mark::Unvisited {
+                continue;
+            }
+            marks[root.index()] = Mark::OnStack;
+            let mut stack = vec![(root, graph.edges(root))];
+            while let Some((ni, edges)) = stack.last_mut() {
+                if let Some(edge) = edges.next() {
+                    let next = edge.target();
+                    match marks[next.index()] {
+                        Mark::Unvisited => {
+                            marks[next.index()] = Mark::OnStack;
+                            stack.push((next, graph.edges(next)));
+                        }
+                        Mark::OnStack => back_edges.push(edge.id()),
+                        Mark::Done => {}
+                    }
+                } else {
+                    marks[ni.index()] = Mark::Done;
+                    stack.pop();
+                }
+            }
+        }
+        back_edges
+    }
+}
+
+impl<N, E> Acyclifier<N, E> for DfsAcyclifier {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        Feedback {
+            edges: self.back_edges(graph),
+            optimal: vec![],
+            iterations: 1,
+            status: Status::Complete,
+        }
+    }
+
+    fn validate_relabeled(
+        &self,
+        graph: &Graph<N, E>,
+        ranks: &[usize],
+    ) -> Result<(), SerializeError> {
+        match &self.roots {
+            RootSelection::Given(given) => check_relabeled(graph, ranks, given),
+            _ => Ok(()),
+        }
+    }
+
+    fn feedback_relabeled(&self, graph: &Graph<N, E>, ranks: &[usize]) -> Feedback {
+        self.relabeled(|ni| relabel(graph, ranks, ni))
+            .feedback(graph)
     }
 }
 
@@ -41,8 +136,10 @@ impl<N: Display + Clone, E> Acyclifier<N, E> for DfsAcyclifier {
 pub mod tests {
     use crate::Acyclifier;
 
-    use super::DfsAcyclifier;
-    use petgraph::algo::{min_spanning_tree, toposort};
+    use super::{DfsAcyclifier, RootSelection};
+    use crate::rank::PageRank;
+    use crate::serialize::toposort;
+    use petgraph::algo::{is_cyclic_directed, min_spanning_tree};
     use petgraph::data::FromElements;
     use petgraph::graph::NodeIndex;
     use petgraph::Graph;
@@ -50,17 +147,55 @@ pub mod tests {
 
     #[test]
     fn test_ser() {
-        let mut g = Graph::<i32, ()>::new();
-        for i in 0..5 {
-            g.add_node(i);
-        }
-        for (f, t) in &[(0, 1), (1, 2), (2, 0), (1, 3)] {
-            g.add_edge(NodeIndex::new(*f), NodeIndex::new(*t), ());
-        }
-        let acy = DfsAcyclifier;
+        let mut g = make_g(5, &[(0, 1), (1, 2), (2, 0), (1, 3)]);
+        let acy = DfsAcyclifier::default();
+        acy.acyclify(&mut g);
+        // 1 has the most outgoing edges, so the DFS starts there and cuts 0 -> 1
+        assert_eq!(g.edge_count(), 3);
+        assert!(g.find_edge(NodeIndex::new(0), NodeIndex::new(1)).is_none());
+        let res = toposort(&g).unwrap();
+        assert_eq!(res, to_nis(&[1, 2, 0, 3, 4]));
+    }
+
+    #[test]
+    fn test_root_selection() {
+        let edges = &[(0, 1), (1, 2), (2, 0), (1, 3)];
+
+        let mut g = make_g(5, edges);
+        let acy = DfsAcyclifier {
+            roots: RootSelection::InsertionOrder,
+        };
+        acy.acyclify(&mut g);
+        assert!(g.find_edge(NodeIndex::new(2), NodeIndex::new(0)).is_none());
+        assert_eq!(toposort(&g).unwrap(), to_nis(&[0, 1, 2, 3, 4]));
+
+        let mut g = make_g(5, edges);
+        let acy = DfsAcyclifier {
+            roots: RootSelection::Given(to_nis(&[2])),
+        };
+        acy.acyclify(&mut g);
+        assert!(g.find_edge(NodeIndex::new(1), NodeIndex::new(2)).is_none());
+        assert_eq!(toposort(&g).unwrap(), to_nis(&[2, 0, 1, 3, 4]));
+
+        // 1 ranks highest, so the DFS starts there and cuts the edge back into it
+        let mut g = make_g(5, edges);
+        let acy = DfsAcyclifier {
+            roots: RootSelection::PageRank(PageRank::default()),
+        };
         acy.acyclify(&mut g);
-        let res = toposort(&g, None).unwrap();
-        dbg!(res);
+        assert!(g.find_edge(NodeIndex::new(0), NodeIndex::new(1)).is_none());
+    }
+
+    #[test]
+    fn test_self_loop_and_nested_cycles() {
+        let mut g = make_g(4, &[(0, 0), (0, 1), (1, 2), (2, 1), (2, 3), (3, 0)]);
+        DfsAcyclifier::default().acyclify(&mut g);
+        assert!(!is_cyclic_directed(&g));
+        assert_eq!(g.edge_count(), 3);
+    }
+
+    fn to_nis(idxs: &[usize]) -> Vec<NodeIndex> {
+        idxs.iter().map(|i| NodeIndex::new(*i)).collect()
     }
 
     fn make_g(n: usize, edges: &[(usize, usize)]) -> Graph<i32, ()> {
@@ -79,7 +214,7 @@ pub mod tests {
         let g1 = make_g(5, &[(0, 1), (2, 0), (1, 2), (0, 3)]);
         let res = min_spanning_tree(&g1);
         let mst_g = Graph::<i32, ()>::from_elements(res);
-        print_graph(&mst_g, NodeIndex::new(0));
+        print_graph(&mst_g, NodeIndex::new(0)).unwrap();
         // 0
         // ├─ 3
         // └─ 1
@@ -88,7 +223,7 @@ pub mod tests {
         let g2 = make_g(5, &[(0, 1), (1, 2), (2, 0), (0, 3)]);
         let res = min_spanning_tree(&g2);
         let mst_g = Graph::<i32, ()>::from_elements(res);
-        print_graph(&mst_g, NodeIndex::new(0));
+        print_graph(&mst_g, NodeIndex::new(0)).unwrap();
         // 0
         // ├─ 3
         // └─ 1
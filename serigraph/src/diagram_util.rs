@@ -0,0 +1,126 @@
+use petgraph::graph::EdgeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// `graph` as a Mermaid flowchart, `removed` edges are drawn dashed red
+///
+/// Node labels come from `Display`, nodes are named `n<index>`.
+pub fn write_mermaid<N: Display, E, W: Write>(
+    graph: &Graph<N, E>,
+    removed: &[EdgeIndex],
+    out: &mut W,
+) -> io::Result<()> {
+    writeln!(out, "flowchart TD")?;
+    for ni in graph.node_indices() {
+        let label = graph[ni].to_string().replace('"', "#quot;");
+        writeln!(out, "    n{}[\"{}\"]", ni.index(), label)?;
+    }
+    for e in graph.edge_references() {
+        let arrow = if removed.contains(&e.id()) {
+            "-.->"
+        } else {
+            "-->"
+        };
+        writeln!(
+            out,
+            "    n{} {} n{}",
+            e.source().index(),
+            arrow,
+            e.target().index()
+        )?;
+    }
+    // links are numbered in the order they are written, which is edge index order
+    let mut removed = removed.iter().map(|e| e.index()).collect::<Vec<_>>();
+    removed.sort_unstable();
+    removed.dedup();
+    if !removed.is_empty() {
+        let links = removed
+            .iter()
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(out, "    linkStyle {} stroke:red", links)?;
+    }
+    Ok(())
+}
+
+/// `graph` as a PlantUML diagram, `removed` edges are drawn dashed red
+///
+/// Node labels come from `Display`, nodes are named `n<index>`.
+pub fn write_plantuml<N: Display, E, W: Write>(
+    graph: &Graph<N, E>,
+    removed: &[EdgeIndex],
+    out: &mut W,
+) -> io::Result<()> {
+    writeln!(out, "@startuml")?;
+    for ni in graph.node_indices() {
+        let label = graph[ni].to_string().replace('"', "'");
+        writeln!(out, "rectangle \"{}\" as n{}", label, ni.index())?;
+    }
+    for e in graph.edge_references() {
+        let arrow = if removed.contains(&e.id()) {
+            "-[#red,dashed]->"
+        } else {
+            "-->"
+        };
+        writeln!(
+            out,
+            "n{} {} n{}",
+            e.source().index(),
+            arrow,
+            e.target().index()
+        )?;
+    }
+    writeln!(out, "@enduml")
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::EdgeIndex;
+    use petgraph::Graph;
+
+    use super::{write_mermaid, write_plantuml};
+
+    fn graph() -> Graph<&'static str, ()> {
+        Graph::<(), ()>::from_edges([(0, 1), (1, 0), (1, 2)])
+            .map(|ni, _| ["a", "b \"c\"", "d"][ni.index()], |_, _| ())
+    }
+
+    #[test]
+    fn test_mermaid() {
+        let mut out = vec![];
+        write_mermaid(&graph(), &[EdgeIndex::new(1)], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"flowchart TD
+    n0["a"]
+    n1["b #quot;c#quot;"]
+    n2["d"]
+    n0 --> n1
+    n1 -.-> n0
+    n1 --> n2
+    linkStyle 1 stroke:red
+"#
+        );
+    }
+
+    #[test]
+    fn test_plantuml() {
+        let mut out = vec![];
+        write_plantuml(&graph(), &[EdgeIndex::new(1)], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"@startuml
+rectangle "a" as n0
+rectangle "b 'c'" as n1
+rectangle "d" as n2
+n0 --> n1
+n1 -[#red,dashed]-> n0
+n1 --> n2
+@enduml
+"#
+        );
+    }
+}
@@ -1,42 +1,90 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use petgraph::algo::tarjan_scc;
 use petgraph::dot::{Config, Dot};
-use petgraph::graph::NodeIndex;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
 use petgraph::Graph;
 use std::fmt::Display;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{self, Write};
 use std::path::Path;
 
-fn dbg_nis(nis: &Vec<NodeIndex>) -> String {
+fn dbg_nis(nis: &[NodeIndex]) -> String {
     nis.iter()
         .map(|ni| ni.index().to_string())
         .collect::<Vec<_>>()
         .join(" -> ")
 }
 
-pub fn dump_cycles<N: Display + Default, E: Display + Clone>(
+/// write `cycles` as DOT, each in a `cluster_cycle_<i>` block with its own copy of its nodes
+///
+/// The last node of a cycle links back to the first, as in `cycles::Cycles`. Node labels and
+/// edge labels come from `Display`. Consecutive nodes that are not linked are an error.
+pub fn write_cycles<N: Display, E: Display, W: Write>(
     graph: &Graph<N, E>,
-    cycle_nis: Vec<NodeIndex>,
-    path: &Path,
+    cycles: &[Vec<NodeIndex>],
+    out: &mut W,
 ) -> Result<()> {
-    let mut g = Graph::<N, E>::new();
-    for i in 0..graph.node_count() {
-        g.add_node(N::default());
+    let mut edges = vec![];
+    for cycle in cycles {
+        if cycle.is_empty() {
+            bail!("empty cycle");
+        }
+        let mut cycle_edges = vec![];
+        for (i, a) in cycle.iter().enumerate() {
+            let b = cycle[(i + 1) % cycle.len()];
+            match graph.find_edge(*a, b) {
+                Some(e) => cycle_edges.push(e),
+                None => bail!("not a cycle: {}", dbg_nis(cycle)),
+            }
+        }
+        edges.push(cycle_edges);
     }
-    for i in 0..cycle_nis.len() {
-        let (a, b) = (cycle_nis[i], cycle_nis[(i + 1) % cycle_nis.len()]);
-        let e = graph[graph.find_edge(a, b).unwrap()].clone();
-        g.add_edge(cycle_nis[i], cycle_nis[(i + 1) % cycle_nis.len()], e);
+
+    writeln!(out, "digraph {{")?;
+    for (i, (cycle, cycle_edges)) in cycles.iter().zip(edges).enumerate() {
+        writeln!(out, "    subgraph cluster_cycle_{} {{", i)?;
+        for ni in cycle {
+            writeln!(
+                out,
+                "        c{}_{} [ label = \"{}\" ]",
+                i,
+                ni.index(),
+                escape(&graph[*ni].to_string())
+            )?;
+        }
+        for e in cycle_edges {
+            let (a, b) = graph.edge_endpoints(e).unwrap();
+            writeln!(
+                out,
+                "        c{}_{} -> c{}_{} [ label = \"{}\" ]",
+                i,
+                a.index(),
+                i,
+                b.index(),
+                escape(&graph[e].to_string())
+            )?;
+        }
+        writeln!(out, "    }}")?;
     }
+    writeln!(out, "}}")?;
+    Ok(())
+}
 
+/// `write_cycles` into a file at `path`
+pub fn dump_cycles<N: Display, E: Display>(
+    graph: &Graph<N, E>,
+    cycles: &[Vec<NodeIndex>],
+    path: &Path,
+) -> Result<()> {
+    let mut out = vec![];
+    write_cycles(graph, cycles, &mut out)?;
     let mut f = OpenOptions::new()
         .create(true)
         .truncate(true)
         .write(true)
         .open(path)?;
-    let dot_str = format!("{}", Dot::with_config(&g, &[Config::NodeIndexLabel,]));
-    f.write(dot_str.as_bytes())?;
-
+    f.write_all(&out)?;
     Ok(())
 }
 
@@ -47,6 +95,201 @@ pub fn dump_dot<N: Display, E: Display>(graph: &Graph<N, E>, path: &Path) -> Res
         .write(true)
         .open(path)?;
     let dot_str = format!("{}", Dot::with_config(&graph, &[Config::NodeIndexLabel,]));
-    f.write(dot_str.as_bytes())?;
+    f.write_all(dot_str.as_bytes())?;
     Ok(())
 }
+
+/// DOT output of a graph with node labels, removed edges and clusters
+///
+/// Node labels come from `Display`, nodes are named by their index.
+pub struct DotWriter<'a, N, E> {
+    graph: &'a Graph<N, E>,
+    removed: Vec<bool>,
+    clusters: Vec<(String, Vec<NodeIndex>)>,
+    edge_label: Option<EdgeLabel<'a, E>>,
+}
+
+type EdgeLabel<'a, E> = Box<dyn Fn(&E) -> String + 'a>;
+
+impl<'a, N: Display, E> DotWriter<'a, N, E> {
+    pub fn new(graph: &'a Graph<N, E>) -> Self {
+        DotWriter {
+            graph,
+            removed: vec![false; graph.edge_count()],
+            clusters: vec![],
+            edge_label: None,
+        }
+    }
+
+    /// draw `edges` dashed red, such as `Feedback::edges`
+    pub fn removed(mut self, edges: &[EdgeIndex]) -> Self {
+        for e in edges {
+            self.removed[e.index()] = true;
+        }
+        self
+    }
+
+    /// label edges with `label`, edges are unlabeled otherwise
+    pub fn edge_label(mut self, label: impl Fn(&E) -> String + 'a) -> Self {
+        self.edge_label = Some(Box::new(label));
+        self
+    }
+
+    /// a `cluster_scc_<i>` block for every strongly connected component with more than one node
+    pub fn sccs(mut self) -> Self {
+        let mut sccs = tarjan_scc(self.graph)
+            .into_iter()
+            .filter(|scc| scc.len() > 1)
+            .map(|mut scc| {
+                scc.sort();
+                scc
+            })
+            .collect::<Vec<_>>();
+        sccs.sort();
+        self.clusters = sccs
+            .into_iter()
+            .enumerate()
+            .map(|(i, scc)| (format!("cluster_scc_{}", i), scc))
+            .collect();
+        self
+    }
+
+    /// a `cluster_layer_<i>` block for every layer, such as those of `layering::layers`
+    pub fn layers(mut self, layers: Vec<Vec<NodeIndex>>) -> Self {
+        self.clusters = layers
+            .into_iter()
+            .enumerate()
+            .map(|(i, layer)| (format!("cluster_layer_{}", i), layer))
+            .collect();
+        self
+    }
+
+    fn write_node<W: Write>(&self, out: &mut W, ni: NodeIndex, indent: &str) -> io::Result<()> {
+        writeln!(
+            out,
+            "{}{} [ label = \"{}\" ]",
+            indent,
+            ni.index(),
+            escape(&self.graph[ni].to_string())
+        )
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "digraph {{")?;
+        let mut clustered = vec![false; self.graph.node_count()];
+        for (name, nodes) in self.clusters.iter() {
+            writeln!(out, "    subgraph {} {{", name)?;
+            for ni in nodes {
+                clustered[ni.index()] = true;
+                self.write_node(out, *ni, "        ")?;
+            }
+            writeln!(out, "    }}")?;
+        }
+        for ni in self.graph.node_indices() {
+            if !clustered[ni.index()] {
+                self.write_node(out, ni, "    ")?;
+            }
+        }
+        for e in self.graph.edge_references() {
+            let mut attributes = vec![];
+            if let Some(label) = self.edge_label.as_ref() {
+                attributes.push(format!("label = \"{}\"", escape(&label(e.weight()))));
+            }
+            if self.removed[e.id().index()] {
+                attributes.push("style = dashed, color = red".to_string());
+            }
+            write!(out, "    {} -> {}", e.source().index(), e.target().index())?;
+            if !attributes.is_empty() {
+                write!(out, " [ {} ]", attributes.join(", "))?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{write_cycles, DotWriter};
+    use crate::eades_acyclifier::EadesAcyclifier;
+    use crate::Acyclifier;
+
+    fn graph() -> Graph<&'static str, u32> {
+        Graph::<(), u32>::from_edges([(0, 1, 5), (1, 0, 6), (1, 2, 7)])
+            .map(|ni, _| ["a", "b \"c\"", "d"][ni.index()], |_, w| *w)
+    }
+
+    #[test]
+    fn test_dot_writer() {
+        let graph = graph();
+        let mut out = vec![];
+        DotWriter::new(&graph)
+            .removed(&EadesAcyclifier.feedback(&graph).edges)
+            .edge_label(|w| w.to_string())
+            .sccs()
+            .write(&mut out)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"digraph {
+    subgraph cluster_scc_0 {
+        0 [ label = "a" ]
+        1 [ label = "b \"c\"" ]
+    }
+    2 [ label = "d" ]
+    0 -> 1 [ label = "5", style = dashed, color = red ]
+    1 -> 0 [ label = "6" ]
+    1 -> 2 [ label = "7" ]
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_write_cycles() {
+        let graph = graph();
+        let cycles = [0, 1].map(NodeIndex::new).to_vec();
+        let mut out = vec![];
+        write_cycles(&graph, &[cycles.clone(), cycles], &mut out).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains(
+            r#"    subgraph cluster_cycle_1 {
+        c1_0 [ label = "a" ]
+        c1_1 [ label = "b \"c\"" ]
+        c1_0 -> c1_1 [ label = "5" ]
+        c1_1 -> c1_0 [ label = "6" ]
+    }
+"#
+        ));
+        // 2 is a dead end
+        assert!(!dot.contains("\"d\""));
+
+        let broken = [1, 2].map(NodeIndex::new).to_vec();
+        let err = write_cycles(&graph, &[broken], &mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "not a cycle: 1 -> 2");
+    }
+
+    #[test]
+    fn test_dot_layers() {
+        let graph = graph();
+        let layers = vec![
+            vec![NodeIndex::new(1)],
+            vec![NodeIndex::new(0), NodeIndex::new(2)],
+        ];
+        let mut out = vec![];
+        DotWriter::new(&graph)
+            .layers(layers)
+            .write(&mut out)
+            .unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("    subgraph cluster_layer_1 {\n        0 [ label = \"a\" ]\n        2 [ label = \"d\" ]\n    }\n"));
+        assert!(dot.contains("    0 -> 1\n"));
+    }
+}
@@ -0,0 +1,206 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction::{Incoming, Outgoing};
+use petgraph::Graph;
+use std::collections::VecDeque;
+
+use crate::mask::{Feedback, Status};
+use crate::Acyclifier;
+
+/// Eades–Lin–Smyth greedy feedback arc set heuristic.
+///
+/// Sinks are moved to the end of the sequence, sources to the front, and otherwise the node
+/// with the largest `out-degree - in-degree` goes to the front.
+/// The edges pointing backwards in the resulting sequence are removed.
+#[derive(Default, Debug)]
+pub struct EadesAcyclifier;
+
+struct Degrees {
+    outs: Vec<usize>,
+    ins: Vec<usize>,
+    removed: Vec<bool>,
+    sinks: VecDeque<usize>,
+    sources: VecDeque<usize>,
+    /// lazily invalidated stacks of nodes keyed by `out - in + offset`
+    buckets: Vec<Vec<usize>>,
+    offset: usize,
+    top: usize,
+}
+
+impl Degrees {
+    fn delta(&self, v: usize) -> usize {
+        self.outs[v] + self.offset - self.ins[v]
+    }
+
+    fn push(&mut self, v: usize) {
+        if self.outs[v] == 0 {
+            self.sinks.push_back(v);
+        } else if self.ins[v] == 0 {
+            self.sources.push_back(v);
+        } else {
+            let delta = self.delta(v);
+            self.buckets[delta].push(v);
+            self.top = self.top.max(delta);
+        }
+    }
+
+    fn pop_sink(&mut self) -> Option<usize> {
+        while let Some(v) = self.sinks.pop_front() {
+            if !self.removed[v] && self.outs[v] == 0 {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn pop_source(&mut self) -> Option<usize> {
+        while let Some(v) = self.sources.pop_front() {
+            if !self.removed[v] && self.ins[v] == 0 {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn pop_max(&mut self) -> Option<usize> {
+        loop {
+            while let Some(v) = self.buckets[self.top].pop() {
+                if !self.removed[v] && self.delta(v) == self.top {
+                    return Some(v);
+                }
+            }
+            if self.top == 0 {
+                return None;
+            }
+            self.top -= 1;
+        }
+    }
+}
+
+/// vertex sequence of Eades–Lin–Smyth, computed in `O(V + E)`
+pub fn eades_ordering<N, E>(graph: &Graph<N, E>) -> Vec<NodeIndex> {
+    let n = graph.node_count();
+    let mut outs = vec![0; n];
+    let mut ins = vec![0; n];
+    for e in graph.edge_references() {
+        if e.source() != e.target() {
+            outs[e.source().index()] += 1;
+            ins[e.target().index()] += 1;
+        }
+    }
+    let offset = ins.iter().cloned().max().unwrap_or(0);
+    let n_buckets = outs.iter().cloned().max().unwrap_or(0) + offset + 1;
+    let mut degrees = Degrees {
+        outs,
+        ins,
+        removed: vec![false; n],
+        sinks: VecDeque::new(),
+        sources: VecDeque::new(),
+        buckets: vec![vec![]; n_buckets],
+        offset,
+        top: 0,
+    };
+    // pushed in reverse so that ties in a bucket pop the smallest index first
+    for v in (0..n).rev() {
+        degrees.push(v);
+    }
+    degrees.sinks.make_contiguous().reverse();
+    degrees.sources.make_contiguous().reverse();
+
+    let mut head = Vec::with_capacity(n);
+    let mut tail = Vec::new();
+    let mut remaining = n;
+    while remaining > 0 {
+        let (v, is_sink) = if let Some(v) = degrees.pop_sink() {
+            (v, true)
+        } else if let Some(v) = degrees.pop_source() {
+            (v, false)
+        } else if let Some(v) = degrees.pop_max() {
+            (v, false)
+        } else {
+            break;
+        };
+        degrees.removed[v] = true;
+        remaining -= 1;
+        if is_sink {
+            tail.push(NodeIndex::new(v));
+        } else {
+            head.push(NodeIndex::new(v));
+        }
+
+        let ni = NodeIndex::new(v);
+        for u in graph.neighbors_directed(ni, Incoming) {
+            let u = u.index();
+            if u != v && !degrees.removed[u] {
+                degrees.outs[u] -= 1;
+                degrees.push(u);
+            }
+        }
+        for w in graph.neighbors_directed(ni, Outgoing) {
+            let w = w.index();
+            if w != v && !degrees.removed[w] {
+                degrees.ins[w] -= 1;
+                degrees.push(w);
+            }
+        }
+    }
+    head.extend(tail.into_iter().rev());
+    head
+}
+
+/// edges that do not point forward in `order`, including self loops
+pub fn backward_edges<N, E>(graph: &Graph<N, E>, order: &[NodeIndex]) -> Vec<EdgeIndex> {
+    let mut position = vec![0; graph.node_count()];
+    for (i, ni) in order.iter().enumerate() {
+        position[ni.index()] = i;
+    }
+    graph
+        .edge_references()
+        .filter(|e| position[e.source().index()] >= position[e.target().index()])
+        .map(|e| e.id())
+        .collect()
+}
+
+impl<N, E> Acyclifier<N, E> for EadesAcyclifier {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        let order = eades_ordering(graph);
+        Feedback {
+            edges: backward_edges(graph, &order),
+            optimal: vec![],
+            iterations: 1,
+            status: Status::Complete,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::is_cyclic_directed;
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{eades_ordering, EadesAcyclifier};
+    use crate::Acyclifier;
+
+    fn to_nis(idxs: &[usize]) -> Vec<NodeIndex> {
+        idxs.iter().map(|i| NodeIndex::new(*i)).collect()
+    }
+
+    #[test]
+    fn test_eades_ordering() {
+        // 3 is a source and 4 a sink, the cycle 0 -> 1 -> 2 -> 0 is broken at 2 -> 0
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 2), (2, 0), (3, 0), (2, 4)]);
+        let order = eades_ordering(&graph);
+        assert_eq!(order, to_nis(&[3, 0, 1, 2, 4]));
+    }
+
+    #[test]
+    fn test_acyclify() {
+        let mut graph =
+            Graph::<i32, ()>::from_edges([(0, 1), (1, 2), (2, 0), (1, 0), (2, 3), (3, 3), (3, 1)]);
+        EadesAcyclifier.acyclify(&mut graph);
+        assert!(!is_cyclic_directed(&graph));
+        // the self loop plus two edges is the minimum feedback arc set here
+        assert_eq!(graph.edge_count(), 4);
+    }
+}
@@ -0,0 +1,175 @@
+use petgraph::graph::{EdgeReference, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+
+use crate::rank::PageRank;
+
+/// the graph as `OutGoingAcyclifier` has cut it so far
+pub struct CutState<'a, N, E> {
+    pub graph: &'a Graph<N, E>,
+    pub(crate) out_degrees: &'a [usize],
+    pub(crate) in_degrees: &'a [usize],
+}
+
+impl<'a, N, E> CutState<'a, N, E> {
+    /// outgoing edges of `ni` that have not been cut
+    pub fn out_degree(&self, ni: NodeIndex) -> usize {
+        self.out_degrees[ni.index()]
+    }
+
+    /// incoming edges of `ni` that have not been cut
+    pub fn in_degree(&self, ni: NodeIndex) -> usize {
+        self.in_degrees[ni.index()]
+    }
+}
+
+/// picks the edge `OutGoingAcyclifier` cuts from a cycle
+///
+/// The edge with the highest score is cut, ties go to the edge into the smallest node index.
+/// Closures `Fn(&CutState<N, E>, EdgeReference<E>) -> f64` are scorers too.
+pub trait EdgeScorer<N, E> {
+    /// whatever the scorer computes once per graph before any edge is cut
+    type State;
+
+    fn prepare(&self, graph: &Graph<N, E>) -> Self::State;
+
+    fn score(&self, state: &Self::State, cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64;
+}
+
+impl<N, E, F> EdgeScorer<N, E> for F
+where
+    F: Fn(&CutState<N, E>, EdgeReference<E>) -> f64,
+{
+    type State = ();
+
+    fn prepare(&self, _graph: &Graph<N, E>) {}
+
+    fn score(&self, _state: &(), cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64 {
+        self(cut, edge)
+    }
+}
+
+/// cut the edge into the node with the most outgoing edges left
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxOutDegree;
+
+impl<N, E> EdgeScorer<N, E> for MaxOutDegree {
+    type State = ();
+
+    fn prepare(&self, _graph: &Graph<N, E>) {}
+
+    fn score(&self, _state: &(), cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64 {
+        cut.out_degree(edge.target()) as f64
+    }
+}
+
+/// cut the edge into the node with the fewest incoming edges left, the least linked note
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinInDegree;
+
+impl<N, E> EdgeScorer<N, E> for MinInDegree {
+    type State = ();
+
+    fn prepare(&self, _graph: &Graph<N, E>) {}
+
+    fn score(&self, _state: &(), cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64 {
+        -(cut.in_degree(edge.target()) as f64)
+    }
+}
+
+/// cut the edge into the node with the lowest PageRank on the uncut graph
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct LowestPageRank(pub PageRank);
+
+impl<N, E> EdgeScorer<N, E> for LowestPageRank {
+    type State = Vec<f64>;
+
+    fn prepare(&self, graph: &Graph<N, E>) -> Vec<f64> {
+        self.0.rank(graph)
+    }
+
+    fn score(&self, ranks: &Vec<f64>, _cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64 {
+        -ranks[edge.target().index()]
+    }
+}
+
+/// cut the edge into the oldest note, the closure reads a timestamp from a note's metadata
+#[derive(Debug, Clone, Copy)]
+pub struct Oldest<F>(pub F);
+
+impl<N, E, F: Fn(&N) -> f64> EdgeScorer<N, E> for Oldest<F> {
+    type State = ();
+
+    fn prepare(&self, _graph: &Graph<N, E>) {}
+
+    fn score(&self, _state: &(), cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64 {
+        -(self.0)(&cut.graph[edge.target()])
+    }
+}
+
+/// cut the edge into the newest note, the closure reads a timestamp from a note's metadata
+#[derive(Debug, Clone, Copy)]
+pub struct Newest<F>(pub F);
+
+impl<N, E, F: Fn(&N) -> f64> EdgeScorer<N, E> for Newest<F> {
+    type State = ();
+
+    fn prepare(&self, _graph: &Graph<N, E>) {}
+
+    fn score(&self, _state: &(), cut: &CutState<N, E>, edge: EdgeReference<E>) -> f64 {
+        (self.0)(&cut.graph[edge.target()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::{EdgeIndex, EdgeReference};
+    use petgraph::visit::EdgeRef;
+    use petgraph::Graph;
+
+    use super::{CutState, EdgeScorer, LowestPageRank, MaxOutDegree, MinInDegree, Newest, Oldest};
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+    use crate::rank::PageRank;
+    use crate::Acyclifier;
+
+    /// the cycles 0 -> 1 -> 2 -> 0 and 0 -> 1 -> 3 -> 2 -> 0
+    fn graph() -> Graph<f64, ()> {
+        let graph = Graph::<f64, ()>::from_edges([(0, 1), (1, 2), (2, 0), (1, 3), (3, 2)]);
+        // node weights are creation times
+        graph.map(|ni, _| [30.0, 10.0, 20.0, 40.0][ni.index()], |_, _| ())
+    }
+
+    fn cut_edges<S: EdgeScorer<f64, ()>>(scorer: S) -> Vec<EdgeIndex> {
+        OutGoingAcyclifier::new(scorer).feedback(&graph()).edges
+    }
+
+    #[test]
+    fn test_scorers() {
+        // 1 has the most outgoing edges, so 0 -> 1 goes
+        assert_eq!(cut_edges(MaxOutDegree), vec![EdgeIndex::new(0)]);
+        // 0, 1 and 3 all have a single incoming edge, the smallest index wins
+        assert_eq!(cut_edges(MinInDegree), vec![EdgeIndex::new(2)]);
+        assert_eq!(cut_edges(Oldest(|t: &f64| *t)), vec![EdgeIndex::new(0)]);
+        // cutting 1 -> 3 leaves 0 -> 1 -> 2 -> 0, where 0 is the newest
+        assert_eq!(
+            cut_edges(Newest(|t: &f64| *t)),
+            vec![EdgeIndex::new(3), EdgeIndex::new(2)]
+        );
+        // 3 ranks lowest, then 1
+        assert_eq!(
+            cut_edges(LowestPageRank(PageRank::default())),
+            vec![EdgeIndex::new(3), EdgeIndex::new(0)]
+        );
+        let into_two = |_: &CutState<f64, ()>, e: EdgeReference<()>| {
+            if e.target().index() == 2 {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        assert_eq!(
+            cut_edges(into_two),
+            vec![EdgeIndex::new(4), EdgeIndex::new(1)]
+        );
+    }
+}
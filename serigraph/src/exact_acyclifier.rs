@@ -0,0 +1,70 @@
+use petgraph::Graph;
+
+use crate::mask::Feedback;
+use crate::weighted_acyclifier::{into_feedback, solve_components, SccSolution};
+use crate::Acyclifier;
+
+/// removes a minimum number of edges from every strongly connected component with at most
+/// `max_exact_size` nodes, at most `weighted_acyclifier::MAX_EXACT_LIMIT`, and falls back to
+/// the Eades–Lin–Smyth heuristic above that
+#[derive(Debug)]
+pub struct ExactAcyclifier {
+    pub max_exact_size: usize,
+}
+
+impl Default for ExactAcyclifier {
+    fn default() -> Self {
+        ExactAcyclifier { max_exact_size: 20 }
+    }
+}
+
+impl ExactAcyclifier {
+    /// removed edges per strongly connected component and whether they are minimal
+    pub fn solve<N, E>(&self, graph: &Graph<N, E>) -> Vec<SccSolution> {
+        solve_components(graph, |_| 1.0, self.max_exact_size)
+    }
+}
+
+impl<N, E> Acyclifier<N, E> for ExactAcyclifier {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        into_feedback(self.solve(graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::is_cyclic_directed;
+    use petgraph::Graph;
+
+    use super::ExactAcyclifier;
+    use crate::Acyclifier;
+
+    /// two triangles sharing the edge 0 -> 1, plus a separate 2-cycle
+    fn graph() -> Graph<i32, ()> {
+        Graph::from_edges([(0, 1), (1, 2), (2, 0), (1, 3), (3, 0), (4, 5), (5, 4)])
+    }
+
+    #[test]
+    fn test_solve() {
+        let solutions = ExactAcyclifier::default().solve(&graph());
+        assert_eq!(solutions.len(), 2);
+        assert!(solutions.iter().all(|s| s.optimal && s.removed.len() == 1));
+
+        let acy = ExactAcyclifier { max_exact_size: 3 };
+        let solutions = acy.solve(&graph());
+        let optimal = solutions
+            .iter()
+            .map(|s| (s.nodes.len(), s.optimal))
+            .collect::<Vec<_>>();
+        assert!(optimal.contains(&(4, false)));
+        assert!(optimal.contains(&(2, true)));
+    }
+
+    #[test]
+    fn test_acyclify() {
+        let mut g = graph();
+        ExactAcyclifier::default().acyclify(&mut g);
+        assert!(!is_cyclic_directed(&g));
+        assert_eq!(g.edge_count(), 5);
+    }
+}
@@ -0,0 +1,452 @@
+use anyhow::{anyhow, bail, Result};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// a weight as text, strings as they are and anything else as JSON, `None` for null
+fn to_text<T: Serialize>(weight: &T) -> Result<Option<String>> {
+    Ok(match serde_json::to_value(weight)? {
+        Value::Null => None,
+        Value::String(s) => Some(s),
+        v => Some(v.to_string()),
+    })
+}
+
+/// the weight `to_text` wrote, a missing text is null
+fn from_text<T: DeserializeOwned>(text: Option<&str>) -> Result<T> {
+    match text {
+        None => Ok(serde_json::from_value(Value::Null)?),
+        Some(text) => serde_json::from_value(Value::String(text.to_string()))
+            .or_else(|_| serde_json::from_str(text))
+            .map_err(|e| anyhow!("cannot read {:?}: {}", text, e)),
+    }
+}
+
+/// a node weight from its label, without one null if the weight takes it, else the id
+fn from_label<T: DeserializeOwned>(label: Option<&str>, id: &str) -> Result<T> {
+    match label {
+        Some(_) => from_text(label),
+        None => from_text(None).or_else(|_| from_text(Some(id))),
+    }
+}
+
+/// a weight from a JSON value, or from its text where the weight is a string such as a numeric id
+fn from_json<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    serde_json::from_value(value.clone()).or_else(|_| from_text(to_text(value)?.as_deref()))
+}
+
+/// `text` safe in XML content and attribute values
+///
+/// Line breaks and tabs are character references, attribute values would turn them into spaces.
+pub(crate) fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+        .replace('\t', "&#9;")
+}
+
+fn read_string(mut input: impl Read) -> Result<String> {
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// `graph` as GraphML, node weights go to the `label` key and edge weights to `weight`
+pub fn write_graphml<N: Serialize, E: Serialize, W: Write>(
+    graph: &Graph<N, E>,
+    out: &mut W,
+) -> Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+    writeln!(
+        out,
+        r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+    )?;
+    writeln!(
+        out,
+        r#"  <key id="weight" for="edge" attr.name="weight" attr.type="string"/>"#
+    )?;
+    writeln!(out, r#"  <graph id="G" edgedefault="directed">"#)?;
+    for ni in graph.node_indices() {
+        match to_text(&graph[ni])? {
+            Some(label) => writeln!(
+                out,
+                r#"    <node id="n{}"><data key="label">{}</data></node>"#,
+                ni.index(),
+                escape_xml(&label)
+            )?,
+            None => writeln!(out, r#"    <node id="n{}"/>"#, ni.index())?,
+        }
+    }
+    for e in graph.edge_references() {
+        let (source, target) = (e.source().index(), e.target().index());
+        match to_text(e.weight())? {
+            Some(weight) => writeln!(
+                out,
+                r#"    <edge source="n{}" target="n{}"><data key="weight">{}</data></edge>"#,
+                source,
+                target,
+                escape_xml(&weight)
+            )?,
+            None => writeln!(
+                out,
+                r#"    <edge source="n{}" target="n{}"/>"#,
+                source, target
+            )?,
+        }
+    }
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</graphml>")?;
+    Ok(())
+}
+
+/// text of the `data` child of a GraphML node or edge with `key`
+fn graphml_data<'a>(node: roxmltree::Node<'a, '_>, key: Option<&str>) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name("data") && n.attribute("key") == key)
+        .map(|n| n.text().unwrap_or(""))
+}
+
+/// a GraphML graph, the `label` data of a node is its weight, without one null or its id
+pub fn read_graphml<N: DeserializeOwned, E: DeserializeOwned>(
+    input: impl Read,
+) -> Result<Graph<N, E>> {
+    let text = read_string(input)?;
+    let doc = roxmltree::Document::parse(&text)?;
+    // keys are referred to by id, find the ids of the label and weight attributes
+    let key_id = |target: &str, name: &str| {
+        doc.descendants()
+            .find(|n| {
+                n.has_tag_name("key")
+                    && n.attribute("for") == Some(target)
+                    && n.attribute("attr.name") == Some(name)
+            })
+            .and_then(|n| n.attribute("id"))
+    };
+    let (label_key, weight_key) = (key_id("node", "label"), key_id("edge", "weight"));
+
+    let mut graph = Graph::new();
+    let mut indices = HashMap::new();
+    for node in doc.descendants().filter(|n| n.has_tag_name("node")) {
+        let id = node
+            .attribute("id")
+            .ok_or_else(|| anyhow!("node without an id"))?;
+        let weight = from_label(graphml_data(node, label_key), id)?;
+        indices.insert(id, graph.add_node(weight));
+    }
+    for edge in doc.descendants().filter(|n| n.has_tag_name("edge")) {
+        let end = |name: &str| -> Result<NodeIndex> {
+            let id = edge
+                .attribute(name)
+                .ok_or_else(|| anyhow!("edge without a {}", name))?;
+            indices
+                .get(id)
+                .cloned()
+                .ok_or_else(|| anyhow!("edge to unknown node {}", id))
+        };
+        let weight = from_text(graphml_data(edge, weight_key))?;
+        graph.add_edge(end("source")?, end("target")?, weight);
+    }
+    Ok(graph)
+}
+
+/// `graph` as GEXF, numeric edge weights go to `weight` and others to the edge `label`
+pub fn write_gexf<N: Serialize, E: Serialize, W: Write>(
+    graph: &Graph<N, E>,
+    out: &mut W,
+) -> Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<gexf xmlns="http://www.gexf.net/1.2draft" version="1.2">"#
+    )?;
+    writeln!(out, r#"  <graph defaultedgetype="directed">"#)?;
+    writeln!(out, "    <nodes>")?;
+    for ni in graph.node_indices() {
+        match to_text(&graph[ni])? {
+            Some(label) => writeln!(
+                out,
+                r#"      <node id="{}" label="{}"/>"#,
+                ni.index(),
+                escape_xml(&label)
+            )?,
+            None => writeln!(out, r#"      <node id="{}"/>"#, ni.index())?,
+        }
+    }
+    writeln!(out, "    </nodes>")?;
+    writeln!(out, "    <edges>")?;
+    for e in graph.edge_references() {
+        let attribute = match serde_json::to_value(e.weight())? {
+            Value::Null => String::new(),
+            Value::Number(n) => format!(r#" weight="{}""#, n),
+            Value::String(s) => format!(r#" label="{}""#, escape_xml(&s)),
+            v => format!(r#" label="{}""#, escape_xml(&v.to_string())),
+        };
+        writeln!(
+            out,
+            r#"      <edge id="{}" source="{}" target="{}"{}/>"#,
+            e.id().index(),
+            e.source().index(),
+            e.target().index(),
+            attribute
+        )?;
+    }
+    writeln!(out, "    </edges>")?;
+    writeln!(out, "  </graph>")?;
+    writeln!(out, "</gexf>")?;
+    Ok(())
+}
+
+/// a GEXF graph, node labels are node weights and edge weights or labels edge weights
+///
+/// A node without a label is weighted null, or by its id where the weight cannot be null.
+pub fn read_gexf<N: DeserializeOwned, E: DeserializeOwned>(
+    input: impl Read,
+) -> Result<Graph<N, E>> {
+    let text = read_string(input)?;
+    let doc = roxmltree::Document::parse(&text)?;
+    let mut graph = Graph::new();
+    let mut indices = HashMap::new();
+    for node in doc.descendants().filter(|n| n.has_tag_name("node")) {
+        let id = node
+            .attribute("id")
+            .ok_or_else(|| anyhow!("node without an id"))?;
+        let weight = from_label(node.attribute("label"), id)?;
+        indices.insert(id, graph.add_node(weight));
+    }
+    for edge in doc.descendants().filter(|n| n.has_tag_name("edge")) {
+        let end = |name: &str| -> Result<NodeIndex> {
+            let id = edge
+                .attribute(name)
+                .ok_or_else(|| anyhow!("edge without a {}", name))?;
+            indices
+                .get(id)
+                .cloned()
+                .ok_or_else(|| anyhow!("edge to unknown node {}", id))
+        };
+        let weight = from_text(edge.attribute("weight").or_else(|| edge.attribute("label")))?;
+        graph.add_edge(end("source")?, end("target")?, weight);
+    }
+    Ok(graph)
+}
+
+/// `graph` as networkx node-link JSON, node weights go to `label` and edge weights to `weight`
+pub fn write_node_link<N: Serialize, E: Serialize, W: Write>(
+    graph: &Graph<N, E>,
+    out: &mut W,
+) -> Result<()> {
+    let mut nodes = vec![];
+    for ni in graph.node_indices() {
+        let mut node = Map::new();
+        node.insert("id".to_string(), json!(ni.index()));
+        match serde_json::to_value(&graph[ni])? {
+            Value::Null => {}
+            label => {
+                node.insert("label".to_string(), label);
+            }
+        }
+        nodes.push(Value::Object(node));
+    }
+    let mut links = vec![];
+    for e in graph.edge_references() {
+        let mut link = Map::new();
+        link.insert("source".to_string(), json!(e.source().index()));
+        link.insert("target".to_string(), json!(e.target().index()));
+        match serde_json::to_value(e.weight())? {
+            Value::Null => {}
+            weight => {
+                link.insert("weight".to_string(), weight);
+            }
+        }
+        links.push(Value::Object(link));
+    }
+    let data = json!({
+        "directed": true,
+        "multigraph": true,
+        "graph": {},
+        "nodes": nodes,
+        "links": links,
+    });
+    serde_json::to_writer_pretty(&mut *out, &data)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// a networkx node-link JSON graph, a node without `label` is null or else its id
+pub fn read_node_link<N: DeserializeOwned, E: DeserializeOwned>(
+    input: impl Read,
+) -> Result<Graph<N, E>> {
+    let data: Value = serde_json::from_reader(input)?;
+    let list = |name: &str| match data.get(name) {
+        Some(Value::Array(list)) => Ok(list),
+        _ => Err(anyhow!("no {} list", name)),
+    };
+    let mut graph = Graph::new();
+    let mut indices = HashMap::new();
+    for node in list("nodes")? {
+        let id = match node.get("id") {
+            Some(id) => id,
+            None => bail!("node without an id"),
+        };
+        let weight = match node.get("label") {
+            Some(label) => from_json(label)?,
+            None => from_json(&Value::Null).or_else(|_| from_json(id))?,
+        };
+        indices.insert(id.to_string(), graph.add_node(weight));
+    }
+    // networkx names the list `edges` since 3.4
+    let links = list("links").or_else(|_| list("edges"))?;
+    for link in links {
+        let end = |name: &str| -> Result<NodeIndex> {
+            let id = link
+                .get(name)
+                .ok_or_else(|| anyhow!("link without a {}", name))?;
+            indices
+                .get(&id.to_string())
+                .cloned()
+                .ok_or_else(|| anyhow!("link to unknown node {}", id))
+        };
+        let weight = from_json(link.get("weight").unwrap_or(&Value::Null))?;
+        graph.add_edge(end("source")?, end("target")?, weight);
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::visit::EdgeRef;
+    use petgraph::Graph;
+    use serde::{Deserialize, Serialize};
+    use std::fmt::Debug;
+
+    use super::{
+        read_gexf, read_graphml, read_node_link, write_gexf, write_graphml, write_node_link,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        title: String,
+        words: u32,
+    }
+
+    fn edges<N, E: Clone>(graph: &Graph<N, E>) -> Vec<(usize, usize, E)> {
+        graph
+            .edge_references()
+            .map(|e| (e.source().index(), e.target().index(), e.weight().clone()))
+            .collect()
+    }
+
+    fn assert_same<N: Debug + PartialEq, E: Debug + PartialEq + Clone>(
+        a: &Graph<N, E>,
+        b: &Graph<N, E>,
+    ) {
+        assert_eq!(
+            a.node_weights().collect::<Vec<_>>(),
+            b.node_weights().collect::<Vec<_>>()
+        );
+        assert_eq!(edges(a), edges(b));
+    }
+
+    fn strings() -> Graph<String, f64> {
+        let mut graph = Graph::new();
+        let a = graph.add_node("a & <b>\n\tc\r".to_string());
+        let b = graph.add_node("12".to_string());
+        graph.add_edge(a, b, 1.5);
+        graph.add_edge(b, a, 2.0);
+        graph
+    }
+
+    fn notes() -> Graph<Note, ()> {
+        let mut graph = Graph::new();
+        let a = graph.add_node(Note {
+            title: "intro".to_string(),
+            words: 120,
+        });
+        let b = graph.add_node(Note {
+            title: "\"quoted\"".to_string(),
+            words: 7,
+        });
+        graph.add_edge(a, b, ());
+        graph
+    }
+
+    #[test]
+    fn test_graphml() {
+        let mut out = vec![];
+        write_graphml(&strings(), &mut out).unwrap();
+        assert_same(&read_graphml(&out[..]).unwrap(), &strings());
+        let mut out = vec![];
+        write_graphml(&notes(), &mut out).unwrap();
+        assert_same(&read_graphml(&out[..]).unwrap(), &notes());
+    }
+
+    #[test]
+    fn test_gexf() {
+        let mut out = vec![];
+        write_gexf(&strings(), &mut out).unwrap();
+        assert_same(&read_gexf(&out[..]).unwrap(), &strings());
+        // numeric weights read as text where the weight is a string
+        let graph: Graph<String, String> = read_gexf(&out[..]).unwrap();
+        assert_eq!(
+            edges(&graph),
+            vec![(0, 1, "1.5".to_string()), (1, 0, "2.0".to_string())]
+        );
+        let mut out = vec![];
+        write_gexf(&notes(), &mut out).unwrap();
+        assert_same(&read_gexf(&out[..]).unwrap(), &notes());
+    }
+
+    #[test]
+    fn test_node_link() {
+        let mut out = vec![];
+        write_node_link(&strings(), &mut out).unwrap();
+        assert_same(&read_node_link(&out[..]).unwrap(), &strings());
+        let mut out = vec![];
+        write_node_link(&notes(), &mut out).unwrap();
+        assert_same(&read_node_link(&out[..]).unwrap(), &notes());
+    }
+
+    #[test]
+    fn test_unit_weights() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let mut out = vec![];
+        write_graphml(&graph, &mut out).unwrap();
+        assert_same(&read_graphml(&out[..]).unwrap(), &graph);
+        let mut out = vec![];
+        write_gexf(&graph, &mut out).unwrap();
+        assert_same(&read_gexf(&out[..]).unwrap(), &graph);
+        let mut out = vec![];
+        write_node_link(&graph, &mut out).unwrap();
+        assert_same(&read_node_link(&out[..]).unwrap(), &graph);
+    }
+
+    #[test]
+    fn test_read_networkx() {
+        // nx.node_link_data of a graph with named nodes and no attributes
+        let data = r#"{"directed": true, "multigraph": false, "graph": {},
+            "nodes": [{"id": "a"}, {"id": "b"}],
+            "links": [{"source": "a", "target": "b", "weight": 3}]}"#;
+        let graph: Graph<String, f64> = read_node_link(data.as_bytes()).unwrap();
+        assert_eq!(graph[petgraph::graph::NodeIndex::new(1)], "b");
+        assert_eq!(edges(&graph), vec![(0, 1, 3.0)]);
+
+        // numeric ids, labels and weights of a graph read with string weights
+        let data = r#"{"directed": true, "multigraph": false, "graph": {},
+            "nodes": [{"id": 0}, {"id": 1, "label": 7}],
+            "links": [{"source": 0, "target": 1, "weight": 2.5}]}"#;
+        let graph: Graph<String, String> = read_node_link(data.as_bytes()).unwrap();
+        assert_eq!(graph.node_weights().collect::<Vec<_>>(), vec!["0", "7"]);
+        assert_eq!(edges(&graph), vec![(0, 1, "2.5".to_string())]);
+    }
+}
@@ -0,0 +1,158 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{GraphBase, IntoNeighborsDirected, IntoNodeIdentifiers, NodeIndexable};
+use petgraph::Direction::{Incoming, Outgoing};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::serialize::{toposort, SerializeError};
+
+/// how `layers` groups the nodes of an acyclic graph
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layering {
+    /// every node one layer after its deepest prerequisite
+    #[default]
+    LongestPath,
+    /// Coffman–Graham, at most `width` nodes per layer
+    CoffmanGraham { width: usize },
+}
+
+/// nodes of an acyclic view grouped into layers, every edge points to a later layer
+///
+/// Nodes within a layer come in the order `node_identifiers` yields them.
+pub fn layers<G>(graph: G, layering: Layering) -> Result<Vec<Vec<NodeIndex>>, SerializeError>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + GraphBase<NodeId = NodeIndex>,
+{
+    let order = toposort(graph)?;
+    let mut layer_of = vec![0; graph.node_bound()];
+    match layering {
+        Layering::LongestPath => {
+            for ni in order {
+                for next in graph.neighbors_directed(ni, Outgoing) {
+                    layer_of[next.index()] = layer_of[next.index()].max(layer_of[ni.index()] + 1);
+                }
+            }
+        }
+        Layering::CoffmanGraham { width } => {
+            let labeled = coffman_graham_labels(graph);
+            // place from the sinks up, every node above its successors in the lowest open level
+            let mut level = vec![0; graph.node_bound()];
+            let mut counts = vec![];
+            for ni in labeled.iter().rev() {
+                let mut k = graph
+                    .neighbors_directed(*ni, Outgoing)
+                    .map(|next| level[next.index()] + 1)
+                    .max()
+                    .unwrap_or(0);
+                while k < counts.len() && counts[k] >= width.max(1) {
+                    k += 1;
+                }
+                if k == counts.len() {
+                    counts.push(0);
+                }
+                counts[k] += 1;
+                level[ni.index()] = k;
+            }
+            for ni in labeled {
+                layer_of[ni.index()] = counts.len() - 1 - level[ni.index()];
+            }
+        }
+    }
+
+    let mut layers: Vec<Vec<NodeIndex>> = vec![];
+    for ni in graph.node_identifiers() {
+        let layer = layer_of[ni.index()];
+        if layers.len() <= layer {
+            layers.resize(layer + 1, vec![]);
+        }
+        layers[layer].push(ni);
+    }
+    Ok(layers)
+}
+
+/// nodes in Coffman–Graham label order
+///
+/// The next label goes to the node whose prerequisites are all labeled and whose
+/// prerequisite labels, highest first, are lexicographically smallest.
+fn coffman_graham_labels<G>(graph: G) -> Vec<NodeIndex>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + GraphBase<NodeId = NodeIndex>,
+{
+    let mut waiting = vec![0usize; graph.node_bound()];
+    for ni in graph.node_identifiers() {
+        for next in graph.neighbors_directed(ni, Outgoing) {
+            waiting[next.index()] += 1;
+        }
+    }
+    let mut ready = graph
+        .node_identifiers()
+        .filter(|ni| waiting[ni.index()] == 0)
+        .map(|ni| Reverse((vec![], ni)))
+        .collect::<BinaryHeap<_>>();
+
+    let mut labels = vec![0; graph.node_bound()];
+    let mut labeled = vec![];
+    while let Some(Reverse((_, ni))) = ready.pop() {
+        labels[ni.index()] = labeled.len();
+        labeled.push(ni);
+        for next in graph.neighbors_directed(ni, Outgoing) {
+            waiting[next.index()] -= 1;
+            if waiting[next.index()] == 0 {
+                let mut before = graph
+                    .neighbors_directed(next, Incoming)
+                    .map(|prev| labels[prev.index()])
+                    .collect::<Vec<_>>();
+                before.sort_by_key(|label| Reverse(*label));
+                ready.push(Reverse((before, next)));
+            }
+        }
+    }
+    labeled
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{layers, Layering};
+    use crate::serialize::SerializeError;
+
+    fn to_nis(layers: &[&[usize]]) -> Vec<Vec<NodeIndex>> {
+        layers
+            .iter()
+            .map(|l| l.iter().map(|i| NodeIndex::new(*i)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_longest_path() {
+        let mut graph = Graph::<(), ()>::from_edges([(0, 1), (0, 2), (1, 3), (2, 3), (0, 3)]);
+        graph.add_node(());
+        assert_eq!(
+            layers(&graph, Layering::LongestPath).unwrap(),
+            to_nis(&[&[0, 4], &[1, 2], &[3]])
+        );
+    }
+
+    #[test]
+    fn test_coffman_graham() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (0, 2), (0, 3), (3, 4)]);
+        assert_eq!(
+            layers(&graph, Layering::LongestPath).unwrap(),
+            to_nis(&[&[0], &[1, 2, 3], &[4]])
+        );
+        let layered = layers(&graph, Layering::CoffmanGraham { width: 2 }).unwrap();
+        assert_eq!(layered, to_nis(&[&[0], &[1, 3], &[2, 4]]));
+        assert!(layered.iter().all(|layer| layer.len() <= 2));
+    }
+
+    #[test]
+    fn test_layers_cycle() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 0)]);
+        assert!(matches!(
+            layers(&graph, Layering::default()),
+            Err(SerializeError::Cycle(_))
+        ));
+    }
+}
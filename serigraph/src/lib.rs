@@ -1,10 +1,99 @@
+pub mod arborescence_acyclifier;
+pub mod constraints;
+pub mod cycles;
 pub mod dfs_acyclifier;
+pub mod diagram_util;
 pub mod dot_util;
+pub mod eades_acyclifier;
+pub mod edge_scorer;
+pub mod exact_acyclifier;
+pub mod io;
+pub mod layering;
+pub mod locality;
+pub mod mask;
+pub mod metrics;
 pub mod outgoing_acyclifier;
+pub mod rank;
+pub mod report;
+pub mod serialize;
+pub mod svg_util;
+pub mod tie_break;
+pub mod visit;
+pub mod weighted_acyclifier;
 
+use mask::{EdgeMask, Feedback};
 use petgraph::Graph;
-use std::fmt::Display;
+use report::AcyclifyReport;
+use serialize::SerializeError;
 
-pub trait Acyclifier<N: Clone + Display, E> {
-    fn acyclify(&self, graph: &mut Graph<N, E>);
+pub trait Acyclifier<N, E> {
+    /// edges whose removal leaves `graph` acyclic, `graph` itself is left untouched
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback;
+
+    /// remove edges from `graph` until it has no cycles and report what was removed
+    fn acyclify(&self, graph: &mut Graph<N, E>) -> AcyclifyReport<E> {
+        let feedback = self.feedback(graph);
+        AcyclifyReport::apply(graph, feedback)
+    }
+
+    /// the edges of `graph` that are kept, see `EdgeMask::view` for an acyclic view
+    fn acyclic_mask(&self, graph: &Graph<N, E>) -> EdgeMask {
+        EdgeMask::new(graph, &self.feedback(graph))
+    }
+
+    /// an error if the configuration names nodes that are not in `graph`
+    ///
+    /// `feedback` ignores such nodes, the `serialize` functions reject them with this.
+    fn validate(&self, graph: &Graph<N, E>) -> Result<(), SerializeError> {
+        let ranks = (0..graph.node_count()).collect::<Vec<_>>();
+        self.validate_relabeled(graph, &ranks)
+    }
+
+    /// `validate` before `feedback_relabeled`, a node ranked past the end of `graph` is not in
+    /// it
+    fn validate_relabeled(
+        &self,
+        _graph: &Graph<N, E>,
+        _ranks: &[usize],
+    ) -> Result<(), SerializeError> {
+        Ok(())
+    }
+
+    /// `feedback` of a relabeled copy of the graph the acyclifier was configured for, whose
+    /// node `ni` is now `NodeIndex::new(ranks[ni.index()])`
+    ///
+    /// Acyclifiers whose configuration names nodes follow the relabeling, see `TieBroken`.
+    fn feedback_relabeled(&self, graph: &Graph<N, E>, _ranks: &[usize]) -> Feedback {
+        self.feedback(graph)
+    }
+}
+
+impl<N, E, A: Acyclifier<N, E> + ?Sized> Acyclifier<N, E> for &A {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        (**self).feedback(graph)
+    }
+
+    fn acyclify(&self, graph: &mut Graph<N, E>) -> AcyclifyReport<E> {
+        (**self).acyclify(graph)
+    }
+
+    fn acyclic_mask(&self, graph: &Graph<N, E>) -> EdgeMask {
+        (**self).acyclic_mask(graph)
+    }
+
+    fn validate(&self, graph: &Graph<N, E>) -> Result<(), SerializeError> {
+        (**self).validate(graph)
+    }
+
+    fn validate_relabeled(
+        &self,
+        graph: &Graph<N, E>,
+        ranks: &[usize],
+    ) -> Result<(), SerializeError> {
+        (**self).validate_relabeled(graph, ranks)
+    }
+
+    fn feedback_relabeled(&self, graph: &Graph<N, E>, ranks: &[usize]) -> Feedback {
+        (**self).feedback_relabeled(graph, ranks)
+    }
 }
@@ -0,0 +1,221 @@
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{GraphBase, IntoNeighborsDirected, IntoNodeIdentifiers, NodeIndexable};
+use petgraph::Direction::Outgoing;
+use petgraph::Graph;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::serialize::SerializeError;
+
+/// which notes `serialize_clustered` keeps together
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub enum Clusters {
+    /// strongly connected components before acyclification
+    #[default]
+    Sccs,
+    /// communities found by label propagation over links in either direction
+    Communities,
+    /// a cluster id for every node index, such as a shared tag
+    Given(Vec<usize>),
+}
+
+impl Clusters {
+    /// cluster id of every node index of `graph`
+    pub fn ids<N, E>(&self, graph: &Graph<N, E>) -> Vec<usize> {
+        match self {
+            Clusters::Sccs => {
+                let mut ids = vec![0; graph.node_count()];
+                for (id, scc) in tarjan_scc(graph).into_iter().enumerate() {
+                    for ni in scc {
+                        ids[ni.index()] = id;
+                    }
+                }
+                ids
+            }
+            Clusters::Communities => communities(graph),
+            Clusters::Given(ids) => ids.clone(),
+        }
+    }
+}
+
+/// label propagation in node index order
+///
+/// A node keeps its label on ties, otherwise ties go to the larger label.
+fn communities<N, E>(graph: &Graph<N, E>) -> Vec<usize> {
+    let mut labels = (0..graph.node_count()).collect::<Vec<_>>();
+    for _ in 0..20 {
+        let mut changed = false;
+        for ni in graph.node_indices() {
+            let mut counts = HashMap::new();
+            for next in graph.neighbors_undirected(ni).filter(|next| *next != ni) {
+                *counts.entry(labels[next.index()]).or_insert(0) += 1;
+            }
+            let own = counts.get(&labels[ni.index()]).cloned().unwrap_or(0);
+            if let Some((label, count)) = counts
+                .into_iter()
+                .max_by_key(|(label, count)| (*count, *label))
+            {
+                if count > own {
+                    labels[ni.index()] = label;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    labels
+}
+
+/// topological sort that keeps clusters together where the links allow
+///
+/// Once a node of a cluster is placed, its ready nodes come first. A stalled cluster
+/// waits while the most recently started cluster that can go on does, only then is
+/// a new cluster started with the ready node of smallest index. `cluster_of` needs an id
+/// for every node index, any ids will do.
+pub fn toposort_clustered<G>(
+    graph: G,
+    cluster_of: &[usize],
+) -> Result<Vec<NodeIndex>, SerializeError>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + GraphBase<NodeId = NodeIndex>,
+{
+    if cluster_of.len() != graph.node_bound() {
+        return Err(SerializeError::ClusterIds(cluster_of.len()));
+    }
+    // number the clusters from 0, ids such as tag hashes may be arbitrarily large
+    let mut dense = HashMap::new();
+    let cluster_of = cluster_of
+        .iter()
+        .map(|c| {
+            let next = dense.len();
+            *dense.entry(*c).or_insert(next)
+        })
+        .collect::<Vec<_>>();
+    let clusters = dense.len();
+    let mut in_degrees = vec![0usize; graph.node_bound()];
+    let mut remaining = vec![0usize; clusters];
+    for ni in graph.node_identifiers() {
+        remaining[cluster_of[ni.index()]] += 1;
+        for next in graph.neighbors_directed(ni, Outgoing) {
+            in_degrees[next.index()] += 1;
+        }
+    }
+    let mut ready_in = vec![BinaryHeap::new(); clusters];
+    let mut ready = BinaryHeap::new();
+    for ni in graph.node_identifiers() {
+        if in_degrees[ni.index()] == 0 {
+            ready_in[cluster_of[ni.index()]].push(Reverse(ni));
+            ready.push(Reverse(ni));
+        }
+    }
+
+    let mut emitted = vec![false; graph.node_bound()];
+    let mut started = vec![false; clusters];
+    let mut open: Vec<usize> = vec![];
+    let mut order = vec![];
+    loop {
+        while let Some(c) = open.last() {
+            if remaining[*c] > 0 {
+                break;
+            }
+            open.pop();
+        }
+        // nodes started from `ready` stay in their cluster's heap, skip them there
+        let ni = match open.iter().rev().find(|c| {
+            let heap = &mut ready_in[**c];
+            while matches!(heap.peek(), Some(Reverse(ni)) if emitted[ni.index()]) {
+                heap.pop();
+            }
+            !heap.is_empty()
+        }) {
+            Some(c) => ready_in[*c].pop().unwrap().0,
+            None => {
+                // every started cluster waits on others, start the smallest ready node's
+                while matches!(ready.peek(), Some(Reverse(ni)) if emitted[ni.index()]) {
+                    ready.pop();
+                }
+                match ready.pop() {
+                    Some(Reverse(ni)) => {
+                        let c = cluster_of[ni.index()];
+                        if !started[c] {
+                            started[c] = true;
+                            open.push(c);
+                        }
+                        ni
+                    }
+                    None => break,
+                }
+            }
+        };
+        emitted[ni.index()] = true;
+        remaining[cluster_of[ni.index()]] -= 1;
+        order.push(ni);
+        for next in graph.neighbors_directed(ni, Outgoing) {
+            in_degrees[next.index()] -= 1;
+            if in_degrees[next.index()] == 0 {
+                ready_in[cluster_of[next.index()]].push(Reverse(next));
+                ready.push(Reverse(next));
+            }
+        }
+    }
+
+    if order.len() < graph.node_identifiers().count() {
+        let remaining = graph
+            .node_identifiers()
+            .filter(|ni| in_degrees[ni.index()] > 0)
+            .collect();
+        return Err(SerializeError::Cycle(remaining));
+    }
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{toposort_clustered, Clusters};
+    use crate::serialize::{toposort, SerializeError};
+
+    #[test]
+    fn test_toposort_clustered() {
+        // two chains 0 -> 2 -> 4 and 1 -> 3 -> 5, both hanging off 6
+        let graph = Graph::<(), ()>::from_edges([(6, 0), (6, 1), (0, 2), (2, 4), (1, 3), (3, 5)]);
+        let plain = toposort(&graph).unwrap();
+        assert_eq!(plain, [6, 0, 1, 2, 3, 4, 5].map(NodeIndex::new));
+
+        let chains = Clusters::Given(vec![0, 1, 0, 1, 0, 1, 2]);
+        let clustered = toposort_clustered(&graph, &chains.ids(&graph)).unwrap();
+        assert_eq!(clustered, [6, 0, 2, 4, 1, 3, 5].map(NodeIndex::new));
+
+        let large = Clusters::Given(vec![0, usize::MAX, 0, usize::MAX, 0, usize::MAX, 7]);
+        let clustered = toposort_clustered(&graph, &large.ids(&graph)).unwrap();
+        assert_eq!(clustered, [6, 0, 2, 4, 1, 3, 5].map(NodeIndex::new));
+    }
+
+    #[test]
+    fn test_cluster_ids_count() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 2)]);
+        let short = Clusters::Given(vec![0, 1]);
+        assert_eq!(
+            toposort_clustered(&graph, &short.ids(&graph)),
+            Err(SerializeError::ClusterIds(2))
+        );
+    }
+
+    #[test]
+    fn test_communities() {
+        // two triangles joined by 2 -> 3
+        let graph =
+            Graph::<(), ()>::from_edges([(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3)]);
+        let ids = Clusters::Communities.ids(&graph);
+        assert_eq!(ids[0], ids[1]);
+        assert_eq!(ids[1], ids[2]);
+        assert_eq!(ids[3], ids[4]);
+        assert_eq!(ids[4], ids[5]);
+        assert_ne!(ids[0], ids[3]);
+    }
+}
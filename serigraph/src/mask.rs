@@ -0,0 +1,99 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{EdgeFiltered, EdgeRef, FilterEdge};
+use petgraph::Graph;
+
+/// whether an acyclifier got rid of every cycle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Status<Id = NodeIndex> {
+    #[default]
+    Complete,
+    /// the iteration limit was hit while these strongly connected components still had cycles
+    Partial { unresolved: Vec<Vec<Id>> },
+}
+
+impl<Id> Status<Id> {
+    pub fn is_complete(&self) -> bool {
+        matches!(self, Status::Complete)
+    }
+
+    /// the same status with every node replaced by `id(node)`
+    pub fn map_nodes<Id2>(self, id: impl Fn(Id) -> Id2) -> Status<Id2> {
+        match self {
+            Status::Complete => Status::Complete,
+            Status::Partial { unresolved } => Status::Partial {
+                unresolved: unresolved
+                    .into_iter()
+                    .map(|nodes| nodes.into_iter().map(&id).collect())
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// edges an acyclifier wants removed, as indices into the untouched graph
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Feedback {
+    pub edges: Vec<EdgeIndex>,
+    /// components whose edges in `edges` are a minimum feedback arc set
+    pub optimal: Vec<Vec<NodeIndex>>,
+    pub iterations: usize,
+    pub status: Status,
+}
+
+/// the edges of a graph that survive acyclification
+///
+/// Use `view` to get an acyclic `EdgeFiltered` graph while keeping the original intact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeMask {
+    kept: Vec<bool>,
+}
+
+impl EdgeMask {
+    /// every edge of `graph` except those in `feedback`
+    pub fn new<N, E>(graph: &Graph<N, E>, feedback: &Feedback) -> Self {
+        let mut kept = vec![true; graph.edge_count()];
+        for e in feedback.edges.iter() {
+            kept[e.index()] = false;
+        }
+        EdgeMask { kept }
+    }
+
+    pub fn contains(&self, edge: EdgeIndex) -> bool {
+        self.kept.get(edge.index()).cloned().unwrap_or(false)
+    }
+
+    /// `graph` with only the kept edges
+    pub fn view<'a, N, E>(
+        &'a self,
+        graph: &'a Graph<N, E>,
+    ) -> EdgeFiltered<&'a Graph<N, E>, &'a EdgeMask> {
+        EdgeFiltered(graph, self)
+    }
+}
+
+impl<R: EdgeRef<EdgeId = EdgeIndex>> FilterEdge<R> for &EdgeMask {
+    fn include_edge(&self, edge: R) -> bool {
+        self.contains(edge.id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::{is_cyclic_directed, toposort};
+    use petgraph::Graph;
+
+    use crate::eades_acyclifier::EadesAcyclifier;
+    use crate::Acyclifier;
+
+    #[test]
+    fn test_view() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 2), (2, 0), (1, 3)]);
+        let mask = EadesAcyclifier.acyclic_mask(&graph);
+        let view = mask.view(&graph);
+        assert!(!is_cyclic_directed(&view));
+        assert_eq!(toposort(&view, None).unwrap().len(), 4);
+        // the graph itself still has every edge
+        assert_eq!(graph.edge_count(), 4);
+        assert!(is_cyclic_directed(&graph));
+    }
+}
@@ -0,0 +1,146 @@
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+
+use crate::mask::{EdgeMask, Feedback, Status};
+use crate::serialize::{toposort, SerializeError};
+use crate::Acyclifier;
+
+/// how well an order of the nodes of a graph follows its links
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Metrics {
+    /// links into a note that comes earlier, or into the note itself
+    pub backward: usize,
+    /// summed weight of the backward links
+    pub backward_weight: f64,
+    /// mean number of positions a forward link skips ahead, 1 is the very next note
+    pub mean_forward_distance: f64,
+    /// links the acyclifier removed
+    pub removed: usize,
+    /// `removed` over every link, 0 without links
+    pub removed_fraction: f64,
+    /// size over spanned positions of every strongly connected component with more than one
+    /// node, 1 when it reads as one contiguous block
+    pub cluster_locality: Vec<f64>,
+}
+
+impl Metrics {
+    /// score `order`, which holds every node of `graph` once, with `feedback` the edges cut
+    /// to get it and `weight` the weight of a link
+    pub fn new<N, E>(
+        graph: &Graph<N, E>,
+        order: &[NodeIndex],
+        feedback: &Feedback,
+        weight: impl Fn(&E) -> f64,
+    ) -> Self {
+        let mut positions = vec![0; graph.node_count()];
+        for (i, ni) in order.iter().enumerate() {
+            positions[ni.index()] = i;
+        }
+
+        let mut metrics = Metrics {
+            removed: feedback.edges.len(),
+            ..Metrics::default()
+        };
+        let mut forward = 0;
+        let mut forward_distance = 0;
+        for e in graph.edge_references() {
+            let (source, target) = (positions[e.source().index()], positions[e.target().index()]);
+            if target <= source {
+                metrics.backward += 1;
+                metrics.backward_weight += weight(e.weight());
+            } else {
+                forward += 1;
+                forward_distance += target - source;
+            }
+        }
+        if forward > 0 {
+            metrics.mean_forward_distance = forward_distance as f64 / forward as f64;
+        }
+        if graph.edge_count() > 0 {
+            metrics.removed_fraction = metrics.removed as f64 / graph.edge_count() as f64;
+        }
+
+        let mut sccs = tarjan_scc(graph)
+            .into_iter()
+            .filter(|scc| scc.len() > 1)
+            .map(|mut scc| {
+                scc.sort();
+                scc
+            })
+            .collect::<Vec<_>>();
+        sccs.sort();
+        metrics.cluster_locality = sccs
+            .iter()
+            .map(|scc| {
+                let spanned = scc.iter().map(|ni| positions[ni.index()]);
+                let span = spanned.clone().max().unwrap() - spanned.min().unwrap() + 1;
+                scc.len() as f64 / span as f64
+            })
+            .collect();
+        metrics
+    }
+
+    /// mean of `cluster_locality`, 1 without clusters
+    pub fn mean_locality(&self) -> f64 {
+        if self.cluster_locality.is_empty() {
+            return 1.0;
+        }
+        self.cluster_locality.iter().sum::<f64>() / self.cluster_locality.len() as f64
+    }
+}
+
+/// serialize `graph` with `acyclifier` and score the order, see `Metrics::new`
+pub fn evaluate<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    weight: impl Fn(&E) -> f64,
+) -> Result<Metrics, SerializeError>
+where
+    A: Acyclifier<N, E>,
+{
+    acyclifier.validate(graph)?;
+    let feedback = acyclifier.feedback(graph);
+    if let Status::Partial { unresolved } = feedback.status {
+        return Err(SerializeError::Unresolved(unresolved));
+    }
+    let mask = EdgeMask::new(graph, &feedback);
+    let order = toposort(&mask.view(graph))?;
+    Ok(Metrics::new(graph, &order, &feedback, weight))
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{evaluate, Metrics};
+    use crate::mask::Feedback;
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+
+    #[test]
+    fn test_metrics() {
+        let graph = Graph::<(), f64>::from_edges([
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (2, 0, 2.0),
+            (1, 3, 1.0),
+            (3, 4, 0.5),
+        ]);
+        let order = [0, 3, 1, 2, 4].map(NodeIndex::new);
+        let metrics = Metrics::new(&graph, &order, &Feedback::default(), |w| *w);
+        assert_eq!(metrics.backward, 2);
+        assert_eq!(metrics.backward_weight, 3.0);
+        // 0 -> 1 goes 2 ahead, 1 -> 2 goes 1 ahead and 3 -> 4 goes 3 ahead
+        assert_eq!(metrics.mean_forward_distance, 2.0);
+        assert_eq!(metrics.removed, 0);
+        assert_eq!(metrics.cluster_locality, vec![0.75]);
+
+        let metrics = evaluate(&graph, &OutGoingAcyclifier::default(), |w| *w).unwrap();
+        assert_eq!(metrics.backward, 1);
+        assert_eq!(metrics.removed, 1);
+        assert_eq!(metrics.removed_fraction, 0.2);
+        assert_eq!(metrics.mean_locality(), 1.0);
+    }
+}
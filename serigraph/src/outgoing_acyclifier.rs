@@ -1,21 +1,53 @@
-use petgraph::{algo::kosaraju_scc, graph::NodeIndex, Direction::Outgoing, Graph};
-use std::{collections::HashSet, fmt::Display};
+use petgraph::{
+    algo::kosaraju_scc,
+    graph::{EdgeIndex, EdgeReference, NodeIndex},
+    visit::EdgeRef,
+    Direction::{Incoming, Outgoing},
+    Graph,
+};
+use std::cmp::Reverse;
+use std::collections::HashSet;
 
+use crate::dfs_acyclifier::DfsAcyclifier;
+use crate::edge_scorer::{CutState, EdgeScorer, MaxOutDegree};
+use crate::mask::{Feedback, Status};
+use crate::serialize::{relabel, SerializeError};
 use crate::Acyclifier;
 
-pub struct OutGoingAcyclifier {
+#[derive(Debug)]
+pub struct OutGoingAcyclifier<S = MaxOutDegree> {
+    /// stop after this many passes, leaving the remaining components `Partial`
+    ///
+    /// A pass cuts one edge from every component found so far. Components are split again
+    /// lazily, see `feedback`, so a component whose cycles fall apart still loses a single
+    /// edge per pass until it is split.
     pub limit: Option<usize>,
+    /// finish the components left by `limit` with the back edges of a DFS instead
+    pub fallback: Option<DfsAcyclifier>,
+    /// which edge of a cycle gets cut
+    pub scorer: S,
 }
 
 impl Default for OutGoingAcyclifier {
     fn default() -> Self {
-        OutGoingAcyclifier { limit: None }
+        OutGoingAcyclifier::new(MaxOutDegree)
     }
 }
 
-pub fn get_cycle_chain<N, E>(graph: &Graph<N, E>, cycle_set: &Vec<NodeIndex>) -> Vec<NodeIndex> {
+impl<S> OutGoingAcyclifier<S> {
+    pub fn new(scorer: S) -> Self {
+        OutGoingAcyclifier {
+            limit: None,
+            fallback: None,
+            scorer,
+        }
+    }
+}
+
+/// a cycle in `cycle_set`, found by walking from its smallest node until the walk closes
+pub fn get_cycle_chain<N, E>(graph: &Graph<N, E>, cycle_set: &[NodeIndex]) -> Vec<NodeIndex> {
     let cycle_hashset = cycle_set.iter().cloned().collect::<HashSet<_>>();
-    let mut curr = *cycle_hashset.iter().next().unwrap();
+    let mut curr = *cycle_set.iter().min().unwrap();
     let mut visited: HashSet<NodeIndex<u32>> = HashSet::new();
     let mut chain: Vec<NodeIndex<u32>> = Vec::new();
 
@@ -24,8 +56,7 @@ pub fn get_cycle_chain<N, E>(graph: &Graph<N, E>, cycle_set: &Vec<NodeIndex>) ->
         visited.insert(curr);
         let neighbor = graph
             .neighbors_directed(curr, Outgoing)
-            .filter(|i| cycle_hashset.contains(i) && !visited.contains(i))
-            .next();
+            .find(|i| cycle_hashset.contains(i) && !visited.contains(i));
 
         // neighbors is empty means at the end of the cycle
         if let Some(next_ni) = neighbor {
@@ -49,16 +80,14 @@ pub fn get_cycle_chain<N, E>(graph: &Graph<N, E>, cycle_set: &Vec<NodeIndex>) ->
     //     dbg_nis(&chain),
     //     dbg_nis(&chain[index..].to_vec())
     // );
-    return chain[index..].to_vec();
+    chain[index..].to_vec()
 }
 
-pub fn unlink_cycle<N, E>(graph: &mut Graph<N, E>, component: &Vec<NodeIndex>) {
+/// the edge `unlink_cycle` cuts from `component`
+pub fn cycle_edge<N, E>(graph: &Graph<N, E>, component: &[NodeIndex]) -> Option<EdgeIndex> {
     if component.len() == 1 {
         let ni = component[0];
-        if let Some(e) = graph.find_edge(ni, ni) {
-            graph.remove_edge(e);
-        }
-        return;
+        return graph.find_edge(ni, ni);
     }
     // log::debug!("cycle: {:?}", &component);
 
@@ -66,7 +95,12 @@ pub fn unlink_cycle<N, E>(graph: &mut Graph<N, E>, component: &Vec<NodeIndex>) {
     let max_ref_node = chain
         .iter()
         .enumerate()
-        .max_by_key(|(_, ni)| graph.neighbors_directed(**ni, Outgoing).count())
+        .max_by_key(|(_, ni)| {
+            (
+                graph.neighbors_directed(**ni, Outgoing).count(),
+                Reverse(**ni),
+            )
+        })
         .unwrap();
     let parent_node_index = chain[(max_ref_node.0 + chain.len() - 1) % chain.len()];
     // log::debug!(
@@ -74,24 +108,262 @@ pub fn unlink_cycle<N, E>(graph: &mut Graph<N, E>, component: &Vec<NodeIndex>) {
     //     parent_node_index.index(),
     //     max_ref_node.1.index()
     // );
-    let edge = graph.find_edge(parent_node_index, *max_ref_node.1).unwrap();
-    graph.remove_edge(edge);
+    graph.find_edge(parent_node_index, *max_ref_node.1)
 }
 
-impl<N: Display + Clone, E> Acyclifier<N, E> for OutGoingAcyclifier {
-    /// decompose cycles while there are cycles
-    fn acyclify(&self, graph: &mut Graph<N, E>) {
-        let mut count = 0usize;
+pub fn unlink_cycle<N, E>(graph: &mut Graph<N, E>, component: &[NodeIndex]) -> Option<E> {
+    cycle_edge(graph, component).and_then(|edge| graph.remove_edge(edge))
+}
+
+/// marks nodes that are no longer in a component being cut
+const NONE: usize = usize::MAX;
+
+/// a strongly connected component still being cut, its nodes are marked with `id`
+struct Component {
+    id: usize,
+    nodes: Vec<NodeIndex>,
+    /// the walk `Cutter::cut` resumes from, every node on it is `seen`
+    chain: Vec<NodeIndex>,
+    /// nodes to walk from once `chain` runs empty, smallest last
+    starts: Vec<NodeIndex>,
+    /// nodes dropped from `nodes` since the component was found
+    dropped: usize,
+}
+
+/// the graph as `OutGoingAcyclifier::feedback` cuts it, without touching the graph itself
+struct Cutter<'a, N, E> {
+    graph: &'a Graph<N, E>,
+    alive: Vec<bool>,
+    out_degrees: Vec<usize>,
+    in_degrees: Vec<usize>,
+    /// id of the component each node belongs to, or `NONE`
+    component: Vec<usize>,
+    next_id: usize,
+    /// nodes on the chain of their component and their position in it
+    seen: Vec<bool>,
+    position: Vec<usize>,
+}
+
+impl<'a, N, E> Cutter<'a, N, E> {
+    fn new(graph: &'a Graph<N, E>) -> Self {
+        Cutter {
+            graph,
+            alive: vec![true; graph.edge_count()],
+            out_degrees: graph
+                .node_indices()
+                .map(|ni| graph.edges(ni).count())
+                .collect(),
+            in_degrees: graph
+                .node_indices()
+                .map(|ni| graph.edges_directed(ni, Incoming).count())
+                .collect(),
+            component: vec![NONE; graph.node_count()],
+            next_id: 0,
+            seen: vec![false; graph.node_count()],
+            position: vec![0; graph.node_count()],
+        }
+    }
+
+    /// the first alive edge from `from` whose target satisfies `to`
+    fn first_edge(
+        &self,
+        from: NodeIndex,
+        to: impl Fn(NodeIndex) -> bool,
+    ) -> Option<EdgeReference<'a, E>> {
+        self.graph
+            .edges(from)
+            .find(|e| self.alive[e.id().index()] && to(e.target()))
+    }
+
+    /// queue the components of `sccs` that still have a cycle
+    fn push_components(&mut self, sccs: Vec<Vec<NodeIndex>>, queue: &mut Vec<Component>) {
+        for mut nodes in sccs {
+            if nodes.len() == 1 && self.first_edge(nodes[0], |t| t == nodes[0]).is_none() {
+                self.component[nodes[0].index()] = NONE;
+                continue;
+            }
+            let id = self.next_id;
+            self.next_id += 1;
+            nodes.sort();
+            for ni in nodes.iter() {
+                self.component[ni.index()] = id;
+            }
+            let starts = nodes.iter().rev().cloned().collect();
+            queue.push(Component {
+                id,
+                nodes,
+                chain: vec![],
+                starts,
+                dropped: 0,
+            });
+        }
+    }
+
+    /// strongly connected components among the remaining nodes of `component`
+    fn split(&mut self, component: Component) -> Vec<Vec<NodeIndex>> {
+        let id = component.id;
+        for ni in component.chain.iter() {
+            self.seen[ni.index()] = false;
+        }
+        let mut sub = Graph::<NodeIndex, ()>::new();
+        for ni in component.nodes.iter() {
+            if self.component[ni.index()] == id {
+                self.position[ni.index()] = sub.add_node(*ni).index();
+            }
+        }
+        for a in sub.node_indices().collect::<Vec<_>>() {
+            for e in self.graph.edges(sub[a]) {
+                if self.alive[e.id().index()] && self.component[e.target().index()] == id {
+                    sub.add_edge(a, NodeIndex::new(self.position[e.target().index()]), ());
+                }
+            }
+        }
+        kosaraju_scc(&sub)
+            .into_iter()
+            .map(|scc| scc.into_iter().map(|ni| sub[ni]).collect())
+            .collect()
+    }
+
+    /// back edges `dfs` finds among the alive edges inside `components`
+    fn back_edges(&mut self, dfs: &DfsAcyclifier, components: &[Component]) -> Vec<EdgeIndex> {
+        let mut sub = Graph::<NodeIndex, EdgeIndex>::new();
+        let mut in_sub = vec![None; self.graph.node_count()];
+        for ni in components.iter().flat_map(|c| c.nodes.iter()) {
+            let sub_ni = sub.add_node(*ni);
+            self.position[ni.index()] = sub_ni.index();
+            in_sub[ni.index()] = Some(sub_ni);
+        }
+        for a in sub.node_indices().collect::<Vec<_>>() {
+            let id = self.component[sub[a].index()];
+            for e in self.graph.edges(sub[a]) {
+                if self.alive[e.id().index()] && self.component[e.target().index()] == id {
+                    let b = NodeIndex::new(self.position[e.target().index()]);
+                    sub.add_edge(a, b, e.id());
+                }
+            }
+        }
+        // given roots name nodes of the whole graph
+        let dfs = dfs.relabeled(|ni| in_sub.get(ni.index()).cloned().flatten());
+        dfs.back_edges(&sub).into_iter().map(|e| sub[e]).collect()
+    }
+
+    fn visit(&mut self, ni: NodeIndex, chain: &mut Vec<NodeIndex>) {
+        self.seen[ni.index()] = true;
+        self.position[ni.index()] = chain.len();
+        chain.push(ni);
+    }
+
+    /// cut the edge `scorer` likes best from one cycle of `component`
+    ///
+    /// The walk goes on from where the last cut left it instead of starting over. Nodes it
+    /// gets stuck on cannot be on a cycle and are dropped from the component.
+    /// Returns `None` once no cycle is left.
+    fn cut<S: EdgeScorer<N, E>>(
+        &mut self,
+        component: &mut Component,
+        scorer: &S,
+        state: &S::State,
+    ) -> Option<EdgeIndex> {
+        let id = component.id;
+        let mut chain = std::mem::take(&mut component.chain);
         loop {
-            // let sccs = tarjan_scc(&*graph);
-            let sccs = kosaraju_scc(&*graph);
-            // log::debug!("iter: {}/{:?}, {} cycles", count, self.limit, sccs.len());
-            if sccs.len() == graph.node_count() {
-                break;
+            let curr = match chain.last() {
+                Some(curr) => *curr,
+                None => {
+                    let start = std::iter::from_fn(|| component.starts.pop())
+                        .find(|ni| self.component[ni.index()] == id && !self.seen[ni.index()]);
+                    match start {
+                        Some(start) => {
+                            self.visit(start, &mut chain);
+                            continue;
+                        }
+                        None => return None,
+                    }
+                }
+            };
+            let next = self.first_edge(curr, |t| {
+                self.component[t.index()] == id && !self.seen[t.index()]
+            });
+            if let Some(next) = next {
+                self.visit(next.target(), &mut chain);
+                continue;
             }
+            // every neighbor left in the component is on the chain
+            match self.first_edge(curr, |t| self.component[t.index()] == id) {
+                Some(closing) => {
+                    let cycle = &chain[self.position[closing.target().index()]..];
+                    let cut = CutState {
+                        graph: self.graph,
+                        out_degrees: &self.out_degrees,
+                        in_degrees: &self.in_degrees,
+                    };
+                    let (i, edge) = (0..cycle.len())
+                        .map(|i| {
+                            let parent = cycle[(i + cycle.len() - 1) % cycle.len()];
+                            let edge = self.first_edge(parent, |t| t == cycle[i]).unwrap();
+                            (i, edge, scorer.score(state, &cut, edge))
+                        })
+                        .max_by(|(_, a, a_score), (_, b, b_score)| {
+                            a_score.total_cmp(b_score).then(b.target().cmp(&a.target()))
+                        })
+                        .map(|(i, edge, _)| (i, edge))
+                        .unwrap();
+                    self.alive[edge.id().index()] = false;
+                    self.out_degrees[edge.source().index()] -= 1;
+                    self.in_degrees[edge.target().index()] -= 1;
+                    if i > 0 {
+                        // the chain up to the source of `edge` is still a path
+                        let at = self.position[edge.target().index()];
+                        for ni in chain.drain(at..) {
+                            self.seen[ni.index()] = false;
+                            component.starts.push(ni);
+                        }
+                    }
+                    component.chain = chain;
+                    return Some(edge.id());
+                }
+                None => {
+                    self.component[curr.index()] = NONE;
+                    self.seen[curr.index()] = false;
+                    chain.pop();
+                    component.dropped += 1;
+                }
+            }
+        }
+    }
+}
 
-            for component in sccs {
-                unlink_cycle(graph, &component);
+impl<S> OutGoingAcyclifier<S> {
+    /// `feedback` with `fallback` in place of `self.fallback`
+    fn feedback_with<N, E>(&self, graph: &Graph<N, E>, fallback: Option<&DfsAcyclifier>) -> Feedback
+    where
+        S: EdgeScorer<N, E>,
+    {
+        let state = self.scorer.prepare(graph);
+        let mut cutter = Cutter::new(graph);
+        let mut components = vec![];
+        cutter.push_components(kosaraju_scc(graph), &mut components);
+        let mut removed = vec![];
+        let mut count = 0usize;
+        while !components.is_empty() {
+            let mut next = vec![];
+            let mut cut = false;
+            for mut component in components {
+                match cutter.cut(&mut component, &self.scorer, &state) {
+                    Some(edge) => removed.push(edge),
+                    None => continue,
+                }
+                cut = true;
+                if component.dropped > 0 && component.dropped * 8 >= component.nodes.len() {
+                    let sccs = cutter.split(component);
+                    cutter.push_components(sccs, &mut next);
+                } else {
+                    next.push(component);
+                }
+            }
+            components = next;
+            if !cut {
+                break;
             }
             count += 1;
             if let Some(limit) = self.limit {
@@ -100,27 +372,107 @@ impl<N: Display + Clone, E> Acyclifier<N, E> for OutGoingAcyclifier {
                 }
             }
         }
-        // dbg!(count);
+        let mut status = Status::Complete;
+        if !components.is_empty() {
+            // the limit was hit, find out which cycles are really left
+            let mut unresolved = vec![];
+            for component in components {
+                let sccs = cutter.split(component);
+                cutter.push_components(sccs, &mut unresolved);
+            }
+            unresolved.sort_by_key(|c| c.nodes[0]);
+            match fallback {
+                Some(dfs) => removed.extend(cutter.back_edges(dfs, &unresolved)),
+                None if unresolved.is_empty() => {}
+                None => {
+                    status = Status::Partial {
+                        unresolved: unresolved.into_iter().map(|c| c.nodes).collect(),
+                    }
+                }
+            }
+        }
+        Feedback {
+            edges: removed,
+            optimal: vec![],
+            iterations: count,
+            status,
+        }
+    }
+}
+
+impl<N, E, S: EdgeScorer<N, E>> Acyclifier<N, E> for OutGoingAcyclifier<S> {
+    /// decompose cycles while there are cycles
+    ///
+    /// Every pass cuts one edge from each component found so far that still has a cycle.
+    /// Strongly connected components are only recomputed inside a component once an eighth
+    /// of its nodes turned out to be on no cycle, which keeps large graphs close to linear.
+    /// Until then a component whose cycles no longer touch counts once per pass, where
+    /// recomputing after every pass would cut each of its parts.
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        self.feedback_with(graph, self.fallback.as_ref())
+    }
+
+    fn validate_relabeled(
+        &self,
+        graph: &Graph<N, E>,
+        ranks: &[usize],
+    ) -> Result<(), SerializeError> {
+        match &self.fallback {
+            Some(dfs) => Acyclifier::<N, E>::validate_relabeled(dfs, graph, ranks),
+            None => Ok(()),
+        }
+    }
+
+    fn feedback_relabeled(&self, graph: &Graph<N, E>, ranks: &[usize]) -> Feedback {
+        let fallback = self
+            .fallback
+            .as_ref()
+            .map(|dfs| dfs.relabeled(|ni| relabel(graph, ranks, ni)));
+        self.feedback_with(graph, fallback.as_ref())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use petgraph::graph::NodeIndex;
+    use petgraph::algo::{is_cyclic_directed, tarjan_scc};
+    use petgraph::graph::{EdgeIndex, NodeIndex};
     use petgraph::Graph;
 
-    use crate::outgoing_acyclifier::get_cycle_chain;
+    use crate::dfs_acyclifier::DfsAcyclifier;
+    use crate::mask::{EdgeMask, Status};
+    use crate::outgoing_acyclifier::{get_cycle_chain, OutGoingAcyclifier};
+    use crate::Acyclifier;
 
     fn to_nis(idxs: Vec<usize>) -> Vec<NodeIndex> {
         idxs.iter().map(|i| NodeIndex::new(*i)).collect::<Vec<_>>()
     }
 
+    /// sparse graph with one large component, edges from a fixed xorshift sequence
+    fn random_graph(n: usize, m: usize) -> Graph<(), ()> {
+        let mut state = 0x2545f4914f6cdd1du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+        let mut graph = Graph::new();
+        for _ in 0..n {
+            graph.add_node(());
+        }
+        for _ in 0..m {
+            let (a, b) = (next(), next());
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), ());
+        }
+        graph
+    }
+
     /// 0 -> 1 -> 2 -> 0 : {0, 1, 2}
     #[test]
     pub fn test_get_cycle1() {
         let edges = vec![(0, 1), (1, 2), (2, 0)];
-        let mut graph = Graph::<i32, i32>::from_edges(edges);
-        let cycle = get_cycle_chain(&mut graph, &to_nis(vec![0, 1, 2]).iter().cloned().collect());
+        let graph = Graph::<i32, i32>::from_edges(edges);
+        let cycle = get_cycle_chain(&graph, &to_nis(vec![0, 1, 2]));
         assert_eq!(cycle, to_nis(vec![0, 1, 2]));
     }
 
@@ -128,8 +480,119 @@ mod tests {
     #[test]
     pub fn test_get_cycle2() {
         let edges = vec![(0, 1), (1, 2), (2, 1)];
-        let mut graph = Graph::<i32, i32>::from_edges(edges);
-        let cycle = get_cycle_chain(&mut graph, &to_nis(vec![0, 1, 2]).iter().cloned().collect());
+        let graph = Graph::<i32, i32>::from_edges(edges);
+        let cycle = get_cycle_chain(&graph, &to_nis(vec![0, 1, 2]));
         assert_eq!(cycle, to_nis(vec![1, 2]));
     }
+
+    /// every component loses one edge per pass
+    #[test]
+    pub fn test_feedback_per_component() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3)]);
+        let feedback = OutGoingAcyclifier::default().feedback(&graph);
+        assert_eq!(feedback.edges.len(), 2);
+        assert_eq!(feedback.iterations, 1);
+    }
+
+    #[test]
+    pub fn test_feedback_large() {
+        let graph = random_graph(5000, 15000);
+        let mask = OutGoingAcyclifier::default().acyclic_mask(&graph);
+        assert!(!is_cyclic_directed(&mask.view(&graph)));
+
+        let limited = OutGoingAcyclifier {
+            limit: Some(2),
+            ..OutGoingAcyclifier::default()
+        }
+        .feedback(&graph);
+        assert_eq!(limited.iterations, 3);
+    }
+
+    /// the cut of 2 -> 3 leaves two cycles in one component, they go one pass after another
+    #[test]
+    pub fn test_pass_per_component_found() {
+        let graph = Graph::<i32, ()>::from_edges([
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (2, 3),
+            (3, 4),
+            (4, 5),
+            (5, 3),
+            (5, 0),
+            (3, 6),
+            (3, 7),
+        ]);
+        let feedback = OutGoingAcyclifier::default().feedback(&graph);
+        assert_eq!(feedback.edges, [3, 2, 6].map(EdgeIndex::new));
+        assert_eq!(feedback.iterations, 3);
+
+        let limited = OutGoingAcyclifier {
+            limit: Some(1),
+            ..OutGoingAcyclifier::default()
+        }
+        .feedback(&graph);
+        assert_eq!(limited.edges, [3, 2].map(EdgeIndex::new));
+        assert_eq!(
+            limited.status,
+            Status::Partial {
+                unresolved: vec![[3, 4, 5].map(NodeIndex::new).to_vec()]
+            }
+        );
+    }
+
+    /// the size `OutGoingAcyclifier` is built for fits on a small thread stack
+    #[test]
+    pub fn test_feedback_small_stack() {
+        let graph = random_graph(50_000, 150_000);
+        let feedback = std::thread::Builder::new()
+            .stack_size(2 << 20)
+            .spawn(move || {
+                let feedback = OutGoingAcyclifier::default().feedback(&graph);
+                (graph, feedback)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        let (graph, feedback) = feedback;
+        assert!(!is_cyclic_directed(
+            &EdgeMask::new(&graph, &feedback).view(&graph)
+        ));
+    }
+
+    #[test]
+    pub fn test_limit_status() {
+        let graph = random_graph(5000, 15000);
+        let limited = OutGoingAcyclifier {
+            limit: Some(0),
+            ..OutGoingAcyclifier::default()
+        };
+        let feedback = limited.feedback(&graph);
+        let unresolved = match &feedback.status {
+            Status::Partial { unresolved } => unresolved.clone(),
+            Status::Complete => panic!("one pass cannot resolve every cycle"),
+        };
+        // every cycle left is inside an unresolved component
+        let mut in_unresolved = vec![false; graph.node_count()];
+        for ni in unresolved.iter().flatten() {
+            in_unresolved[ni.index()] = true;
+        }
+        let mask = EdgeMask::new(&graph, &feedback);
+        for scc in tarjan_scc(&mask.view(&graph)) {
+            if scc.len() > 1 {
+                assert!(scc.iter().all(|ni| in_unresolved[ni.index()]));
+            }
+        }
+
+        let finished = OutGoingAcyclifier {
+            limit: Some(0),
+            fallback: Some(DfsAcyclifier::default()),
+            ..OutGoingAcyclifier::default()
+        }
+        .feedback(&graph);
+        assert_eq!(finished.status, Status::Complete);
+        assert!(!is_cyclic_directed(
+            &EdgeMask::new(&graph, &finished).view(&graph)
+        ));
+    }
 }
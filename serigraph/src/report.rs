@@ -0,0 +1,195 @@
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::Graph;
+
+use crate::mask::{Feedback, Status};
+
+/// an edge removed by an acyclifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedEdge<E, Id = NodeIndex> {
+    pub source: Id,
+    pub target: Id,
+    pub weight: E,
+    /// index into `AcyclifyReport::sccs` of the component the edge was cut from
+    pub scc: Option<usize>,
+}
+
+/// a strongly connected component of the graph before acyclification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SccReport<Id = NodeIndex> {
+    pub nodes: Vec<Id>,
+    /// whether the edges removed from this component are a minimum feedback arc set
+    pub optimal: bool,
+}
+
+/// what `Acyclifier::acyclify` did to the graph
+///
+/// Reports for graphs other than `Graph` name their nodes by `Id`, see `visit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcyclifyReport<E, Id = NodeIndex> {
+    pub removed: Vec<RemovedEdge<E, Id>>,
+    /// components with a cycle, ordered by their smallest node index
+    pub sccs: Vec<SccReport<Id>>,
+    pub iterations: usize,
+    /// `Partial` if cycles are left in the graph
+    pub status: Status<Id>,
+    scc_of: Vec<Option<usize>>,
+}
+
+impl<E> AcyclifyReport<E> {
+    /// an empty report for `graph`, taking its cyclic components before anything is removed
+    pub fn new<N>(graph: &Graph<N, E>) -> Self {
+        let mut sccs = tarjan_scc(graph)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || graph.find_edge(scc[0], scc[0]).is_some())
+            .map(|mut nodes| {
+                nodes.sort();
+                SccReport {
+                    nodes,
+                    optimal: false,
+                }
+            })
+            .collect::<Vec<_>>();
+        sccs.sort_by_key(|scc| scc.nodes[0]);
+        let mut report = AcyclifyReport {
+            removed: vec![],
+            sccs,
+            iterations: 0,
+            status: Status::Complete,
+            scc_of: vec![],
+        };
+        report.index_sccs(graph.node_count());
+        report
+    }
+
+    /// rebuild the lookup behind `scc_of` for node indices below `bound`
+    pub(crate) fn index_sccs(&mut self, bound: usize) {
+        self.scc_of = vec![None; bound];
+        for (i, scc) in self.sccs.iter().enumerate() {
+            for ni in scc.nodes.iter() {
+                self.scc_of[ni.index()] = Some(i);
+            }
+        }
+    }
+
+    /// index into `sccs` of the component containing `ni`
+    pub fn scc_of(&self, ni: NodeIndex) -> Option<usize> {
+        self.scc_of.get(ni.index()).cloned().flatten()
+    }
+
+    /// remove `edge` from `graph` and record it
+    pub fn remove<N>(&mut self, graph: &mut Graph<N, E>, edge: EdgeIndex) {
+        let (source, target) = graph.edge_endpoints(edge).expect("edge must exist");
+        let weight = graph.remove_edge(edge).unwrap();
+        let scc = self
+            .scc_of(source)
+            .filter(|i| self.scc_of(target) == Some(*i));
+        self.removed.push(RemovedEdge {
+            source,
+            target,
+            weight,
+            scc,
+        });
+    }
+
+    /// remove the edges of `feedback` from `graph` and report them
+    pub fn apply<N>(graph: &mut Graph<N, E>, feedback: Feedback) -> Self {
+        let mut report = AcyclifyReport::new(graph);
+        for nodes in feedback.optimal.iter() {
+            if let Some(i) = nodes.first().and_then(|ni| report.scc_of(*ni)) {
+                report.sccs[i].optimal = true;
+            }
+        }
+        report.remove_all(graph, feedback.edges);
+        report.iterations = feedback.iterations;
+        report.status = feedback.status;
+        report
+    }
+
+    /// remove all of `edges`, which must index into `graph` as it is now
+    pub fn remove_all<N>(&mut self, graph: &mut Graph<N, E>, mut edges: Vec<EdgeIndex>) {
+        // remove from the highest index so that petgraph's swap-remove keeps the rest valid
+        edges.sort_by(|a, b| b.cmp(a));
+        edges.dedup();
+        let start = self.removed.len();
+        for edge in edges {
+            self.remove(graph, edge);
+        }
+        self.removed[start..].reverse();
+    }
+
+    /// the same report with every node index replaced by `id(index)`
+    ///
+    /// `scc_of` is left empty, the components are still listed in `sccs`.
+    pub fn map_nodes<Id>(self, id: impl Fn(NodeIndex) -> Id) -> AcyclifyReport<E, Id> {
+        AcyclifyReport {
+            removed: self
+                .removed
+                .into_iter()
+                .map(|edge| RemovedEdge {
+                    source: id(edge.source),
+                    target: id(edge.target),
+                    weight: edge.weight,
+                    scc: edge.scc,
+                })
+                .collect(),
+            sccs: self
+                .sccs
+                .into_iter()
+                .map(|scc| SccReport {
+                    nodes: scc.nodes.into_iter().map(&id).collect(),
+                    optimal: scc.optimal,
+                })
+                .collect(),
+            iterations: self.iterations,
+            status: self.status.map_nodes(&id),
+            scc_of: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{RemovedEdge, SccReport};
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+    use crate::Acyclifier;
+
+    #[test]
+    fn test_report() {
+        let mut graph =
+            Graph::<i32, &str>::from_edges([(0, 1, "a"), (1, 2, "b"), (2, 0, "c"), (1, 3, "d")]);
+        graph.add_edge(NodeIndex::new(3), NodeIndex::new(3), "e");
+        let report = OutGoingAcyclifier::default().acyclify(&mut graph);
+        assert_eq!(
+            report.sccs,
+            vec![
+                SccReport {
+                    nodes: vec![NodeIndex::new(0), NodeIndex::new(1), NodeIndex::new(2)],
+                    optimal: false,
+                },
+                SccReport {
+                    nodes: vec![NodeIndex::new(3)],
+                    optimal: false,
+                },
+            ]
+        );
+        assert!(report.removed.contains(&RemovedEdge {
+            source: NodeIndex::new(0),
+            target: NodeIndex::new(1),
+            weight: "a",
+            scc: Some(0),
+        }));
+        assert!(report.removed.contains(&RemovedEdge {
+            source: NodeIndex::new(3),
+            target: NodeIndex::new(3),
+            weight: "e",
+            scc: Some(1),
+        }));
+        assert_eq!(report.removed.len(), 2);
+        assert_eq!(report.iterations, 1);
+        assert!(report.status.is_complete());
+    }
+}
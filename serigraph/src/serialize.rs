@@ -0,0 +1,682 @@
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{
+    EdgeRef, GraphBase, IntoEdgeReferences, IntoNeighborsDirected, IntoNodeIdentifiers,
+    NodeIndexable,
+};
+use petgraph::Direction::{Incoming, Outgoing};
+use petgraph::Graph;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+use crate::constraints::Constraints;
+use crate::layering::{layers, Layering};
+use crate::locality::{toposort_clustered, Clusters};
+use crate::mask::{EdgeMask, Feedback, Status};
+use crate::rank::PageRank;
+use crate::tie_break::{TieBreak, TieBroken};
+use crate::visit::Compact;
+use crate::Acyclifier;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerializeError<Id = NodeIndex> {
+    /// the acyclified graph still contains cycles made of these nodes
+    Cycle(Vec<Id>),
+    /// the acyclifier hit its limit while these components still had cycles
+    Unresolved(Vec<Vec<Id>>),
+    /// the ordering constraints put these nodes before each other
+    Constraint(Vec<Id>),
+    /// a node named by the configuration that is not in the graph, by the index it was given
+    UnknownNode(NodeIndex),
+    /// there are this many cluster ids instead of one for every node
+    ClusterIds(usize),
+}
+
+impl<Id> SerializeError<Id> {
+    /// the same error with every node replaced by `id(node)`
+    pub fn map_nodes<Id2>(self, id: impl Fn(Id) -> Id2) -> SerializeError<Id2> {
+        match self {
+            SerializeError::Cycle(nis) => SerializeError::Cycle(nis.into_iter().map(id).collect()),
+            SerializeError::Unresolved(sccs) => SerializeError::Unresolved(
+                sccs.into_iter()
+                    .map(|nis| nis.into_iter().map(&id).collect())
+                    .collect(),
+            ),
+            SerializeError::Constraint(nis) => {
+                SerializeError::Constraint(nis.into_iter().map(id).collect())
+            }
+            SerializeError::UnknownNode(ni) => SerializeError::UnknownNode(ni),
+            SerializeError::ClusterIds(count) => SerializeError::ClusterIds(count),
+        }
+    }
+}
+
+impl<Id: Debug> Display for SerializeError<Id> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SerializeError::Cycle(nis) => write!(
+                f,
+                "graph still has cycles after acyclification: {}",
+                nis.iter()
+                    .map(|ni| format!("{:?}", ni))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            SerializeError::Unresolved(sccs) => write!(
+                f,
+                "acyclifier stopped with {} components still cyclic: {}",
+                sccs.len(),
+                sccs.iter()
+                    .map(|nis| format!("{:?}", nis))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            SerializeError::Constraint(nis) => {
+                write!(f, "ordering constraints form a cycle through {:?}", nis)
+            }
+            SerializeError::UnknownNode(ni) => write!(f, "node {:?} is not in the graph", ni),
+            SerializeError::ClusterIds(count) => {
+                write!(
+                    f,
+                    "{} cluster ids do not match the nodes of the graph",
+                    count
+                )
+            }
+        }
+    }
+}
+
+impl<Id: Debug> std::error::Error for SerializeError<Id> {}
+
+/// `SerializeError::UnknownNode` for the first of `nodes` that is not in `graph`
+pub(crate) fn check_nodes<'a, N, E>(
+    graph: &Graph<N, E>,
+    nodes: impl IntoIterator<Item = &'a NodeIndex>,
+) -> Result<(), SerializeError> {
+    match nodes
+        .into_iter()
+        .find(|ni| ni.index() >= graph.node_count())
+    {
+        Some(ni) => Err(SerializeError::UnknownNode(*ni)),
+        None => Ok(()),
+    }
+}
+
+/// node `ni` of the graph that `ranks` relabels, as a node of `graph`
+pub(crate) fn relabel<N, E>(
+    graph: &Graph<N, E>,
+    ranks: &[usize],
+    ni: NodeIndex,
+) -> Option<NodeIndex> {
+    ranks
+        .get(ni.index())
+        .filter(|r| **r < graph.node_count())
+        .map(|r| NodeIndex::new(*r))
+}
+
+/// `check_nodes` for nodes that `ranks` relabels into `graph`
+pub(crate) fn check_relabeled<'a, N, E>(
+    graph: &Graph<N, E>,
+    ranks: &[usize],
+    nodes: impl IntoIterator<Item = &'a NodeIndex>,
+) -> Result<(), SerializeError> {
+    match nodes
+        .into_iter()
+        .find(|ni| relabel(graph, ranks, **ni).is_none())
+    {
+        Some(ni) => Err(SerializeError::UnknownNode(*ni)),
+        None => Ok(()),
+    }
+}
+
+/// topological sort that always emits the ready node with the smallest index first
+pub fn toposort<G>(graph: G) -> Result<Vec<NodeIndex>, SerializeError>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + GraphBase<NodeId = NodeIndex>,
+{
+    toposort_by_key(graph, |ni| ni)
+}
+
+/// topological sort that always emits the ready node with the smallest key first
+///
+/// Works on any petgraph view, such as `EdgeMask::view`.
+pub fn toposort_by_key<G, K: Ord>(
+    graph: G,
+    key: impl Fn(NodeIndex) -> K,
+) -> Result<Vec<NodeIndex>, SerializeError>
+where
+    G: IntoNodeIdentifiers + IntoNeighborsDirected + NodeIndexable + GraphBase<NodeId = NodeIndex>,
+{
+    let mut in_degrees = vec![0usize; graph.node_bound()];
+    for ni in graph.node_identifiers() {
+        for next in graph.neighbors_directed(ni, Outgoing) {
+            in_degrees[next.index()] += 1;
+        }
+    }
+    let mut ready = graph
+        .node_identifiers()
+        .filter(|ni| in_degrees[ni.index()] == 0)
+        .map(|ni| Reverse((key(ni), ni)))
+        .collect::<BinaryHeap<_>>();
+
+    let mut order = vec![];
+    while let Some(Reverse((_, ni))) = ready.pop() {
+        order.push(ni);
+        for next in graph.neighbors_directed(ni, Outgoing) {
+            in_degrees[next.index()] -= 1;
+            if in_degrees[next.index()] == 0 {
+                ready.push(Reverse((key(next), next)));
+            }
+        }
+    }
+
+    if order.len() < graph.node_identifiers().count() {
+        let remaining = graph
+            .node_identifiers()
+            .filter(|ni| in_degrees[ni.index()] > 0)
+            .collect();
+        return Err(SerializeError::Cycle(remaining));
+    }
+    Ok(order)
+}
+
+/// the edges `acyclifier` keeps, unless it left cycles unresolved
+fn complete_mask<N, E, A>(graph: &Graph<N, E>, acyclifier: &A) -> Result<EdgeMask, SerializeError>
+where
+    A: Acyclifier<N, E>,
+{
+    acyclifier.validate(graph)?;
+    let feedback = acyclifier.feedback(graph);
+    if let Status::Partial { unresolved } = feedback.status {
+        return Err(SerializeError::Unresolved(unresolved));
+    }
+    Ok(EdgeMask::new(graph, &feedback))
+}
+
+/// like `serialize_indexed`, but whenever several nodes could come next the one with the
+/// smallest `key` is taken
+pub fn serialize_by_key<N, E, A, K>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    key: impl Fn(NodeIndex) -> K,
+) -> Result<Vec<(NodeIndex, N)>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+    K: Ord,
+{
+    let mask = complete_mask(graph, acyclifier)?;
+    let order = toposort_by_key(&mask.view(graph), key)?;
+    Ok(order
+        .into_iter()
+        .map(|ni| (ni, graph[ni].clone()))
+        .collect())
+}
+
+/// nodes of `graph` in reading order with their indices
+///
+/// The order is taken on an acyclic view of `graph`, which itself is left untouched.
+pub fn serialize_indexed<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+) -> Result<Vec<(NodeIndex, N)>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+{
+    serialize_by_key(graph, acyclifier, |ni| ni)
+}
+
+/// like `serialize_indexed`, but nodes with a higher PageRank on `graph` come first
+/// whenever the links allow it
+pub fn serialize_ranked<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    page_rank: &PageRank,
+) -> Result<Vec<(NodeIndex, N)>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+{
+    let mut positions = vec![0; graph.node_count()];
+    for (i, ni) in page_rank.order(graph).into_iter().enumerate() {
+        positions[ni.index()] = i;
+    }
+    serialize_by_key(graph, acyclifier, |ni| positions[ni.index()])
+}
+
+/// like `serialize_indexed`, but ties are broken by `tie_break` instead of by node index,
+/// both by the acyclifier and when several nodes could come next
+pub fn serialize_tie_broken<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    tie_break: &TieBreak,
+) -> Result<Vec<(NodeIndex, N)>, SerializeError>
+where
+    N: Clone,
+    E: Clone,
+    A: Acyclifier<N, E>,
+{
+    let ranks = tie_break.ranks(graph);
+    let tie_broken = TieBroken {
+        acyclifier,
+        tie_break: tie_break.clone(),
+    };
+    serialize_by_key(graph, &tie_broken, |ni| ranks[ni.index()])
+}
+
+/// like `serialize_indexed`, but `constraints` always hold and links parallel to them are
+/// never cut
+pub fn serialize_constrained<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    constraints: &Constraints,
+) -> Result<Vec<(NodeIndex, N)>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+{
+    let (order, _) = constraints.order(graph, acyclifier)?;
+    Ok(order
+        .into_iter()
+        .map(|ni| (ni, graph[ni].clone()))
+        .collect())
+}
+
+/// like `serialize`, but the order stays as close to `previous` as the links allow
+///
+/// Links on a cycle that point backward in `previous` are cut first, as the previous edition
+/// cut them, and `acyclifier` only breaks the cycles left, which run through new notes.
+/// Notes of `previous` keep their relative order unless the links force them apart, so the
+/// `kendall_tau` distance to it stays small. A new note comes right after its latest
+/// predecessor, without one right before its earliest successor from `previous`.
+pub fn serialize_incremental<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    previous: &[N],
+) -> Result<Vec<N>, SerializeError>
+where
+    N: Clone + Eq + Hash,
+    E: Clone,
+    A: Acyclifier<N, E>,
+{
+    let positions = previous
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n, i))
+        .collect::<HashMap<_, _>>();
+    let position = |ni: NodeIndex| positions.get(&graph[ni]).cloned();
+
+    let mut scc_of = vec![0; graph.node_count()];
+    for (i, scc) in tarjan_scc(graph).into_iter().enumerate() {
+        for ni in scc {
+            scc_of[ni.index()] = i;
+        }
+    }
+    let mut cut = vec![];
+    let mut rest = Graph::with_capacity(graph.node_count(), graph.edge_count());
+    let mut rest_edges = vec![];
+    for ni in graph.node_indices() {
+        rest.add_node(graph[ni].clone());
+    }
+    for e in graph.edge_references() {
+        let (source, target) = (e.source(), e.target());
+        let backward = match (position(source), position(target)) {
+            (Some(s), Some(t)) => t <= s && scc_of[source.index()] == scc_of[target.index()],
+            _ => false,
+        };
+        if backward {
+            cut.push(e.id());
+        } else {
+            rest.add_edge(source, target, e.weight().clone());
+            rest_edges.push(e.id());
+        }
+    }
+
+    let mut order = graph.node_indices().collect::<Vec<_>>();
+    order.sort_by_key(|ni| position(*ni).unwrap_or(usize::MAX));
+    let tie_broken = TieBroken {
+        acyclifier,
+        tie_break: TieBreak::Order(order),
+    };
+    tie_broken.validate(graph)?;
+    let feedback = tie_broken.feedback(&rest);
+    if let Status::Partial { unresolved } = feedback.status {
+        return Err(SerializeError::Unresolved(unresolved));
+    }
+    cut.extend(feedback.edges.iter().map(|e| rest_edges[e.index()]));
+    let feedback = Feedback {
+        edges: cut,
+        ..Feedback::default()
+    };
+    let mask = EdgeMask::new(graph, &feedback);
+    let view = mask.view(graph);
+
+    // notes of `previous` take even slots, new notes the odd slot after their latest
+    // predecessor or before their earliest successor, or the last one
+    let mut slots = graph
+        .node_indices()
+        .map(|ni| position(ni).map(|p| 2 * p + 2))
+        .collect::<Vec<_>>();
+    for ni in toposort(&view)? {
+        if slots[ni.index()].is_some() {
+            continue;
+        }
+        let after = view
+            .neighbors_directed(ni, Incoming)
+            .filter_map(|prev| slots[prev.index()])
+            .max();
+        let before = view
+            .neighbors_directed(ni, Outgoing)
+            .filter_map(|next| position(next).map(|p| 2 * p + 1))
+            .min();
+        slots[ni.index()] = Some(match (after, before) {
+            (Some(slot), _) => slot | 1,
+            (None, Some(slot)) => slot,
+            (None, None) => 2 * previous.len() + 1,
+        });
+    }
+    let order = toposort_by_key(&view, |ni| (slots[ni.index()], ni))?;
+    Ok(order.into_iter().map(|ni| graph[ni].clone()).collect())
+}
+
+/// number of pairs found in both `a` and `b` that they put the other way round
+pub fn kendall_tau<T: Eq + Hash>(a: &[T], b: &[T]) -> usize {
+    let positions = b
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t, i))
+        .collect::<HashMap<_, _>>();
+    let mut ranks = a
+        .iter()
+        .filter_map(|t| positions.get(t).cloned())
+        .collect::<Vec<_>>();
+    inversions(&mut ranks)
+}
+
+/// sort `ranks` and count the pairs that were out of order
+fn inversions(ranks: &mut Vec<usize>) -> usize {
+    if ranks.len() < 2 {
+        return 0;
+    }
+    let mut right = ranks.split_off(ranks.len() / 2);
+    let mut count = inversions(ranks) + inversions(&mut right);
+    let left = std::mem::take(ranks);
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if j == right.len() || (i < left.len() && left[i] <= right[j]) {
+            ranks.push(left[i]);
+            i += 1;
+        } else {
+            count += left.len() - i;
+            ranks.push(right[j]);
+            j += 1;
+        }
+    }
+    count
+}
+
+/// like `serialize_indexed`, but nodes of the same cluster are kept together where the
+/// links allow, see `toposort_clustered`
+pub fn serialize_clustered<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    clusters: &Clusters,
+) -> Result<Vec<(NodeIndex, N)>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+{
+    let cluster_of = clusters.ids(graph);
+    let mask = complete_mask(graph, acyclifier)?;
+    let order = toposort_clustered(&mask.view(graph), &cluster_of)?;
+    Ok(order
+        .into_iter()
+        .map(|ni| (ni, graph[ni].clone()))
+        .collect())
+}
+
+/// node weights of `graph` grouped into layers by `layering`, such as parts of a book
+///
+/// Every link that survives acyclification points into a later layer.
+pub fn serialize_layered<N, E, A>(
+    graph: &Graph<N, E>,
+    acyclifier: &A,
+    layering: Layering,
+) -> Result<Vec<Vec<N>>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+{
+    let mask = complete_mask(graph, acyclifier)?;
+    let layered = layers(&mask.view(graph), layering)?;
+    Ok(layered
+        .into_iter()
+        .map(|layer| layer.into_iter().map(|ni| graph[ni].clone()).collect())
+        .collect())
+}
+
+/// node weights of `graph` in reading order, see `serialize_indexed`
+pub fn serialize<N, E, A>(graph: &Graph<N, E>, acyclifier: &A) -> Result<Vec<N>, SerializeError>
+where
+    N: Clone,
+    A: Acyclifier<N, E>,
+{
+    serialize_indexed(graph, acyclifier).map(|nodes| nodes.into_iter().map(|(_, n)| n).collect())
+}
+
+/// ids of the nodes of any petgraph graph in reading order, such as a `StableGraph` or a
+/// `GraphMap`
+///
+/// Ready nodes are taken in the order `node_identifiers` yields them. Nodes named by
+/// `acyclifier` are the indices `NodeIndexable::to_index` gives.
+pub fn serialize_graph<G, A>(
+    graph: G,
+    acyclifier: &A,
+) -> Result<Vec<G::NodeId>, SerializeError<G::NodeId>>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+    G::EdgeWeight: Clone,
+    A: Acyclifier<G::NodeId, G::EdgeWeight>,
+{
+    let compact = Compact::new(graph);
+    let id = |ni: NodeIndex| compact.graph[ni];
+    let feedback = compact.feedback(acyclifier).map_err(|e| e.map_nodes(id))?;
+    if let Status::Partial { unresolved } = feedback.status {
+        return Err(SerializeError::Unresolved(unresolved).map_nodes(id));
+    }
+    let mask = EdgeMask::new(&compact.graph, &feedback);
+    toposort(&mask.view(&compact.graph))
+        .map(|order| order.into_iter().map(id).collect())
+        .map_err(|e| e.map_nodes(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::NodeIndex;
+    use petgraph::Direction::Incoming;
+    use petgraph::Graph;
+
+    use super::{
+        kendall_tau, serialize, serialize_clustered, serialize_incremental, serialize_indexed,
+        serialize_layered, serialize_ranked, serialize_tie_broken, toposort, SerializeError,
+    };
+    use crate::eades_acyclifier::EadesAcyclifier;
+    use crate::layering::Layering;
+    use crate::locality::Clusters;
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+    use crate::rank::PageRank;
+    use crate::tie_break::TieBreak;
+
+    #[test]
+    fn test_serialize() {
+        let mut graph = Graph::<i32, ()>::new();
+        for n in 0..4 {
+            graph.add_node(n);
+        }
+        for (a, b) in [(0, 1), (1, 2), (2, 0), (1, 3)] {
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), ());
+        }
+        let nodes = serialize(&graph, &OutGoingAcyclifier::default()).unwrap();
+        assert_eq!(nodes, vec![1, 2, 0, 3]);
+        // the caller's graph is left untouched
+        assert_eq!(graph.edge_count(), 4);
+    }
+
+    #[test]
+    fn test_serialize_ranked() {
+        // 1 is a prerequisite of more notes than 0, so it goes first
+        let graph = Graph::<i32, ()>::from_edges([(1, 2), (1, 3), (0, 3), (2, 4), (4, 2)]);
+        let page_rank = PageRank {
+            direction: Incoming,
+            ..PageRank::default()
+        };
+        let nodes = serialize_ranked(&graph, &EadesAcyclifier, &page_rank)
+            .unwrap()
+            .into_iter()
+            .map(|(ni, _)| ni.index())
+            .collect::<Vec<_>>();
+        assert_eq!(nodes, vec![1, 2, 4, 0, 3]);
+    }
+
+    #[test]
+    fn test_serialize_tie_broken() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 0), (2, 3)]);
+        let order = |tie_break: TieBreak| {
+            serialize_tie_broken(&graph, &EadesAcyclifier, &tie_break)
+                .unwrap()
+                .into_iter()
+                .map(|(ni, _)| ni.index())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(TieBreak::Index), vec![0, 1, 2, 3]);
+        let reversed = TieBreak::Order([3, 2, 1, 0].map(NodeIndex::new).to_vec());
+        assert_eq!(order(reversed), vec![2, 3, 1, 0]);
+        assert_eq!(order(TieBreak::Seeded(1)), order(TieBreak::Seeded(1)));
+    }
+
+    #[test]
+    fn test_serialize_incremental() {
+        let previous = ["e", "c", "a", "b"];
+        // "d" is new and links to "a", "b" -> "c" goes against the previous order
+        let mut graph = Graph::<&str, ()>::new();
+        let nis = ["a", "b", "c", "d", "e"].map(|n| graph.add_node(n));
+        for (a, b) in [(0, 1), (1, 2), (2, 0), (3, 0)] {
+            graph.add_edge(nis[a], nis[b], ());
+        }
+        let acyclifier = OutGoingAcyclifier::default();
+        let fresh = serialize(&graph, &acyclifier).unwrap();
+        let incremental = serialize_incremental(&graph, &acyclifier, &previous).unwrap();
+        assert_eq!(fresh, vec!["d", "a", "b", "c", "e"]);
+        assert_eq!(kendall_tau(&previous, &fresh), 5);
+        // "d" goes right before "a" instead of the front
+        assert_eq!(incremental, vec!["e", "c", "d", "a", "b"]);
+        assert_eq!(kendall_tau(&previous, &incremental), 0);
+    }
+
+    #[test]
+    fn test_serialize_incremental_cuts_backward_links() {
+        let previous = ["a", "b", "c", "d", "e"];
+        // "b" has the most outgoing links, so a fresh cut drops "a" -> "b" rather than
+        // "c" -> "a", which points backward in `previous`; "f" and "g" are new
+        let mut graph = Graph::<&str, ()>::new();
+        let nis = ["a", "b", "c", "d", "e", "f", "g"].map(|n| graph.add_node(n));
+        for (a, b) in [(0, 1), (1, 2), (2, 0), (1, 3), (1, 4), (2, 5), (6, 4)] {
+            graph.add_edge(nis[a], nis[b], ());
+        }
+        let acyclifier = OutGoingAcyclifier::default();
+        let fresh = serialize(&graph, &acyclifier).unwrap();
+        assert_eq!(fresh, vec!["b", "c", "a", "d", "f", "g", "e"]);
+        assert_eq!(kendall_tau(&previous, &fresh), 2);
+        let incremental = serialize_incremental(&graph, &acyclifier, &previous).unwrap();
+        // "f" follows its predecessor "c", "g" precedes its successor "e"
+        assert_eq!(incremental, vec!["a", "b", "c", "f", "d", "g", "e"]);
+        assert_eq!(kendall_tau(&previous, &incremental), 0);
+    }
+
+    #[test]
+    fn test_serialize_layered() {
+        let mut graph = Graph::<i32, ()>::new();
+        for n in 0..4 {
+            graph.add_node(n);
+        }
+        for (a, b) in [(0, 1), (1, 2), (2, 0), (1, 3)] {
+            graph.add_edge(NodeIndex::new(a), NodeIndex::new(b), ());
+        }
+        let acyclifier = OutGoingAcyclifier::default();
+        assert_eq!(
+            serialize_layered(&graph, &acyclifier, Layering::LongestPath).unwrap(),
+            vec![vec![1], vec![2, 3], vec![0]]
+        );
+        assert_eq!(
+            serialize_layered(&graph, &acyclifier, Layering::CoffmanGraham { width: 1 }).unwrap(),
+            vec![vec![1], vec![2], vec![3], vec![0]]
+        );
+    }
+
+    #[test]
+    fn test_serialize_clustered() {
+        // the cycle 1 -> 3 -> 5 -> 1 is one component, 0 -> 2 -> 4 runs alongside it
+        let graph = Graph::<i32, ()>::from_edges([(0, 2), (2, 4), (1, 3), (3, 5), (5, 1)]);
+        let order = |nodes: Vec<(NodeIndex, i32)>| {
+            nodes
+                .into_iter()
+                .map(|(ni, _)| ni.index())
+                .collect::<Vec<_>>()
+        };
+        let acyclifier = OutGoingAcyclifier::default();
+        assert_eq!(
+            order(serialize_indexed(&graph, &acyclifier).unwrap()),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            order(serialize_clustered(&graph, &acyclifier, &Clusters::Sccs).unwrap()),
+            vec![0, 1, 3, 5, 2, 4]
+        );
+        // tagging the chain and the cycle apart keeps each together
+        let tags = Clusters::Given(vec![0, 1, 0, 1, 0, 1]);
+        assert_eq!(
+            order(serialize_clustered(&graph, &acyclifier, &tags).unwrap()),
+            vec![0, 2, 4, 1, 3, 5]
+        );
+    }
+
+    #[test]
+    fn test_kendall_tau() {
+        assert_eq!(kendall_tau(&[1, 2, 3], &[1, 2, 3]), 0);
+        assert_eq!(kendall_tau(&[1, 2, 3], &[3, 2, 1]), 3);
+        assert_eq!(kendall_tau(&[4, 1, 2, 3], &[2, 1, 5, 3]), 1);
+        assert_eq!(kendall_tau(&[5, 4, 3, 2, 1, 0], &[0, 1, 2, 3, 4, 5]), 15);
+    }
+
+    #[test]
+    fn test_serialize_unresolved() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 2), (2, 0), (0, 2), (2, 1), (1, 0)]);
+        let limited = OutGoingAcyclifier {
+            limit: Some(0),
+            ..OutGoingAcyclifier::default()
+        };
+        assert_eq!(
+            serialize(&graph, &limited),
+            Err(SerializeError::Unresolved(vec![vec![
+                NodeIndex::new(0),
+                NodeIndex::new(1),
+                NodeIndex::new(2)
+            ]]))
+        );
+    }
+
+    #[test]
+    fn test_toposort_cycle() {
+        let graph = Graph::<i32, ()>::from_edges([(0, 1), (1, 2), (2, 1)]);
+        assert_eq!(
+            toposort(&graph),
+            Err(SerializeError::Cycle(vec![
+                NodeIndex::new(1),
+                NodeIndex::new(2)
+            ]))
+        );
+    }
+}
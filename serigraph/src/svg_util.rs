@@ -0,0 +1,427 @@
+use anyhow::Result;
+use petgraph::graph::EdgeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use std::fmt::Display;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use crate::io::escape_xml;
+use crate::layering::{layers, Layering};
+use crate::mask::{EdgeMask, Feedback};
+
+const NODE_HEIGHT: f64 = 30.0;
+const LAYER_GAP: f64 = 70.0;
+const NODE_GAP: f64 = 20.0;
+const CHAR_WIDTH: f64 = 7.0;
+const MARGIN: f64 = 20.0;
+const SWEEPS: usize = 8;
+
+/// positions of a layered drawing, vertices past the real nodes are dummies on long edges
+struct Layout {
+    /// vertices of every layer, left to right
+    rows: Vec<Vec<usize>>,
+    /// center of every vertex
+    x: Vec<f64>,
+    y: Vec<f64>,
+    width: Vec<f64>,
+    /// vertices every kept edge passes through, from source to target
+    paths: Vec<(EdgeIndex, Vec<usize>)>,
+}
+
+/// Sugiyama layout of `graph` without `removed`: longest path layers, dummy vertices on
+/// long edges, barycenter crossing reduction and averaged left and right placement
+fn layout<N, E>(graph: &Graph<N, E>, removed: &[EdgeIndex], width: &[f64]) -> Result<Layout> {
+    let feedback = Feedback {
+        edges: removed.to_vec(),
+        ..Feedback::default()
+    };
+    let mask = EdgeMask::new(graph, &feedback);
+    let mut rows = layers(&mask.view(graph), Layering::LongestPath)?
+        .into_iter()
+        .map(|layer| layer.into_iter().map(|ni| ni.index()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let mut layer_of = vec![0; graph.node_count()];
+    for (l, row) in rows.iter().enumerate() {
+        for v in row {
+            layer_of[*v] = l;
+        }
+    }
+
+    let mut width = width.to_vec();
+    let mut paths = vec![];
+    for e in graph.edge_references().filter(|e| mask.contains(e.id())) {
+        let (source, target) = (e.source().index(), e.target().index());
+        let mut path = vec![source];
+        for row in rows[layer_of[source] + 1..layer_of[target]].iter_mut() {
+            path.push(width.len());
+            row.push(width.len());
+            width.push(0.0);
+        }
+        path.push(target);
+        paths.push((e.id(), path));
+    }
+    let mut up = vec![vec![]; width.len()];
+    let mut down = vec![vec![]; width.len()];
+    for (_, path) in paths.iter() {
+        for pair in path.windows(2) {
+            down[pair[0]].push(pair[1]);
+            up[pair[1]].push(pair[0]);
+        }
+    }
+
+    // barycenter sweeps, keeping the order with the fewest crossings
+    let mut best = rows.clone();
+    let mut best_crossings = crossings(&rows, &down);
+    for sweep in 0..SWEEPS {
+        if best_crossings == 0 {
+            break;
+        }
+        let (range, neighbors): (Vec<usize>, _) = if sweep % 2 == 0 {
+            ((1..rows.len()).collect(), &up)
+        } else {
+            ((0..rows.len().saturating_sub(1)).rev().collect(), &down)
+        };
+        for l in range {
+            let mut position = vec![0.0; width.len()];
+            for (i, v) in rows[l].iter().enumerate() {
+                position[*v] = i as f64;
+            }
+            let fixed = if sweep % 2 == 0 { l - 1 } else { l + 1 };
+            for (i, v) in rows[fixed].iter().enumerate() {
+                position[*v] = i as f64;
+            }
+            let key = |v: usize| -> f64 {
+                let ns = &neighbors[v];
+                if ns.is_empty() {
+                    position[v]
+                } else {
+                    ns.iter().map(|n| position[*n]).sum::<f64>() / ns.len() as f64
+                }
+            };
+            let mut keyed = rows[l].iter().map(|v| (key(*v), *v)).collect::<Vec<_>>();
+            keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            rows[l] = keyed.into_iter().map(|(_, v)| v).collect();
+        }
+        let count = crossings(&rows, &down);
+        if count < best_crossings {
+            best = rows.clone();
+            best_crossings = count;
+        }
+    }
+    let rows = best;
+
+    // pack every layer, then pull vertices toward their neighbors without overlapping
+    let mut x = vec![0.0; width.len()];
+    for row in rows.iter() {
+        let mut right = 0.0;
+        for v in row {
+            x[*v] = right + width[*v] / 2.0;
+            right += width[*v] + NODE_GAP;
+        }
+    }
+    for sweep in 0..SWEEPS {
+        let (range, neighbors): (Vec<usize>, _) = if sweep % 2 == 0 {
+            ((0..rows.len()).collect(), &up)
+        } else {
+            ((0..rows.len()).rev().collect(), &down)
+        };
+        for l in range {
+            let row = &rows[l];
+            let wanted = row
+                .iter()
+                .map(|v| {
+                    let ns = &neighbors[*v];
+                    if ns.is_empty() {
+                        x[*v]
+                    } else {
+                        ns.iter().map(|n| x[*n]).sum::<f64>() / ns.len() as f64
+                    }
+                })
+                .collect::<Vec<_>>();
+            let gap = |i: usize| (width[row[i]] + width[row[i + 1]]) / 2.0 + NODE_GAP;
+            // both passes keep every gap, and so does their mean
+            let mut left = wanted.clone();
+            for i in 1..row.len() {
+                left[i] = left[i].max(left[i - 1] + gap(i - 1));
+            }
+            let mut right = wanted;
+            for i in (0..row.len().saturating_sub(1)).rev() {
+                right[i] = right[i].min(right[i + 1] - gap(i));
+            }
+            for (i, v) in row.iter().enumerate() {
+                x[*v] = (left[i] + right[i]) / 2.0;
+            }
+        }
+    }
+    let min = (0..width.len())
+        .map(|v| x[v] - width[v] / 2.0)
+        .fold(f64::INFINITY, f64::min);
+    for x in x.iter_mut() {
+        *x += MARGIN - min;
+    }
+
+    let mut y = vec![0.0; width.len()];
+    for (l, row) in rows.iter().enumerate() {
+        for v in row {
+            y[*v] = MARGIN + NODE_HEIGHT / 2.0 + l as f64 * (NODE_HEIGHT + LAYER_GAP);
+        }
+    }
+    Ok(Layout {
+        rows,
+        x,
+        y,
+        width,
+        paths,
+    })
+}
+
+/// crossing segments between every pair of neighboring layers
+fn crossings(rows: &[Vec<usize>], down: &[Vec<usize>]) -> usize {
+    let mut position = vec![0; down.len()];
+    for row in rows {
+        for (i, v) in row.iter().enumerate() {
+            position[*v] = i;
+        }
+    }
+    let mut count = 0;
+    for row in rows {
+        let segments = row
+            .iter()
+            .flat_map(|v| down[*v].iter().map(|w| (position[*v], position[*w])))
+            .collect::<Vec<_>>();
+        for (i, a) in segments.iter().enumerate() {
+            for b in segments[i + 1..].iter() {
+                if (a.0 < b.0 && a.1 > b.1) || (a.0 > b.0 && a.1 < b.1) {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+/// `graph` as an SVG picture in layered layout, `removed` edges are drawn dashed red
+///
+/// `graph` without `removed` must be acyclic, such as the feedback edges of an acyclifier.
+/// Every kept edge points down. Node labels come from `Display`.
+pub fn write_svg<N: Display, E, W: Write>(
+    graph: &Graph<N, E>,
+    removed: &[EdgeIndex],
+    out: &mut W,
+) -> Result<()> {
+    let labels = graph
+        .node_indices()
+        .map(|ni| graph[ni].to_string())
+        .collect::<Vec<_>>();
+    let widths = labels
+        .iter()
+        .map(|label| (label.chars().count() as f64 * CHAR_WIDTH + 16.0).max(NODE_HEIGHT))
+        .collect::<Vec<_>>();
+    let Layout {
+        rows,
+        x,
+        y,
+        width,
+        paths,
+    } = layout(graph, removed, &widths)?;
+    let half = NODE_HEIGHT / 2.0;
+    let right = (0..width.len())
+        .map(|v| x[v] + width[v] / 2.0)
+        .fold(0.0, f64::max);
+    // removed edges bow out to the right, an empty graph is only the margins
+    let (w, h) = (
+        right + MARGIN + 60.0,
+        (rows.len() as f64 * (NODE_HEIGHT + LAYER_GAP) - LAYER_GAP).max(0.0) + 2.0 * MARGIN,
+    );
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}" font-family="sans-serif" font-size="12">"#
+    )?;
+    writeln!(out, "  <defs>")?;
+    for (id, color) in [("arrow", "black"), ("arrow-removed", "red")] {
+        writeln!(
+            out,
+            r#"    <marker id="{id}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto"><path d="M 0 0 L 10 5 L 0 10 z" fill="{color}"/></marker>"#
+        )?;
+    }
+    writeln!(out, "  </defs>")?;
+    for (_, path) in paths.iter() {
+        let points = path
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let y = match i {
+                    0 => y[*v] + half,
+                    i if i == path.len() - 1 => y[*v] - half,
+                    _ => y[*v],
+                };
+                format!("{:.1},{:.1}", x[*v], y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            r#"  <polyline points="{points}" fill="none" stroke="black" marker-end="url(#arrow)"/>"#
+        )?;
+    }
+    let mut removed = removed.to_vec();
+    removed.sort_unstable();
+    removed.dedup();
+    for e in removed {
+        let (s, t) = match graph.edge_endpoints(e) {
+            Some((s, t)) => (s.index(), t.index()),
+            None => continue,
+        };
+        let d = if s == t {
+            let side = x[s] + width[s] / 2.0;
+            format!(
+                "M {:.1} {:.1} C {:.1} {:.1} {:.1} {:.1} {:.1} {:.1}",
+                side,
+                y[s] - half / 2.0,
+                side + 30.0,
+                y[s] - half,
+                side + 30.0,
+                y[s] + half,
+                side,
+                y[s] + half / 2.0
+            )
+        } else if y[s] == y[t] {
+            format!(
+                "M {:.1} {:.1} C {:.1} {:.1} {:.1} {:.1} {:.1} {:.1}",
+                x[s],
+                y[s] - half,
+                x[s],
+                y[s] - half - 40.0,
+                x[t],
+                y[t] - half - 40.0,
+                x[t],
+                y[t] - half
+            )
+        } else {
+            let (start, end) = if y[t] < y[s] {
+                (y[s] - half, y[t] + half)
+            } else {
+                (y[s] + half, y[t] - half)
+            };
+            let bow = 40.0 + (end - start).abs() / 4.0;
+            format!(
+                "M {:.1} {:.1} C {:.1} {:.1} {:.1} {:.1} {:.1} {:.1}",
+                x[s],
+                start,
+                x[s] + bow,
+                start + (end - start) / 3.0,
+                x[t] + bow,
+                end - (end - start) / 3.0,
+                x[t],
+                end
+            )
+        };
+        writeln!(
+            out,
+            r#"  <path d="{d}" fill="none" stroke="red" stroke-dasharray="4 3" marker-end="url(#arrow-removed)"/>"#
+        )?;
+    }
+    for (v, label) in labels.iter().enumerate() {
+        writeln!(
+            out,
+            r#"  <rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="4" fill="white" stroke="black"/>"#,
+            x[v] - width[v] / 2.0,
+            y[v] - half,
+            width[v],
+            NODE_HEIGHT
+        )?;
+        writeln!(
+            out,
+            r#"  <text x="{:.1}" y="{:.1}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+            x[v],
+            y[v],
+            escape_xml(label)
+        )?;
+    }
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+/// `write_svg` into a file at `path`
+pub fn dump_svg<N: Display, E>(
+    graph: &Graph<N, E>,
+    removed: &[EdgeIndex],
+    path: &Path,
+) -> Result<()> {
+    let mut out = vec![];
+    write_svg(graph, removed, &mut out)?;
+    let mut f = OpenOptions::new()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .open(path)?;
+    f.write_all(&out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::EdgeIndex;
+    use petgraph::Graph;
+
+    use super::{crossings, layout, write_svg, NODE_GAP};
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+    use crate::Acyclifier;
+
+    #[test]
+    fn test_layout() {
+        // 0 -> 3 and 1 -> 2 cross in index order, 0 -> 4 spans two layers
+        let graph = Graph::<(), ()>::from_edges([(0, 3), (1, 2), (3, 4), (0, 4)]);
+        let layout = layout(&graph, &[], &[30.0; 5]).unwrap();
+        let mut down = vec![vec![]; layout.x.len()];
+        for (_, path) in layout.paths.iter() {
+            for pair in path.windows(2) {
+                down[pair[0]].push(pair[1]);
+            }
+        }
+        assert_eq!(crossings(&layout.rows, &down), 0);
+        assert_eq!(layout.x.len(), 6);
+        assert_eq!(layout.paths[3].1, vec![0, 5, 4]);
+        for row in layout.rows.iter() {
+            for pair in row.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let gap = (layout.width[a] + layout.width[b]) / 2.0 + NODE_GAP;
+                assert!(layout.x[b] - layout.x[a] >= gap - 1e-9);
+                assert_eq!(layout.y[a], layout.y[b]);
+            }
+        }
+        assert!(layout.y[0] < layout.y[3] && layout.y[3] < layout.y[4]);
+    }
+
+    #[test]
+    fn test_svg() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 2), (2, 0), (2, 2)])
+            .map(|ni, _| ["a", "b & c", "d"][ni.index()], |_, _| ());
+        let mut out = vec![];
+        assert!(write_svg(&graph, &[], &mut out).is_err());
+
+        let removed = OutGoingAcyclifier::default().feedback(&graph).edges;
+        write_svg(&graph, &removed, &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert!(svg.contains(">b &amp; c</text>"));
+        assert_eq!(svg.matches("<polyline").count(), 4 - removed.len());
+        assert_eq!(svg.matches("stroke=\"red\"").count(), removed.len());
+        assert!(removed.contains(&EdgeIndex::new(3)));
+    }
+
+    #[test]
+    fn test_svg_empty() {
+        let graph = Graph::<&str, ()>::new();
+        let mut out = vec![];
+        write_svg(&graph, &[], &mut out).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.contains(r#"width="80" height="40" viewBox="0 0 80 40""#));
+        assert_eq!(svg.matches("<rect").count(), 0);
+    }
+}
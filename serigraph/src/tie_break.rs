@@ -0,0 +1,296 @@
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use std::cmp::Ordering;
+
+use crate::mask::{Feedback, Status};
+use crate::serialize::{check_nodes, check_relabeled, relabel, SerializeError};
+use crate::Acyclifier;
+
+/// order among nodes that an algorithm cannot tell apart
+///
+/// Acyclifiers and `toposort` break ties by node index. Wrap an acyclifier in `TieBroken`,
+/// or serialize with `serialize_tie_broken`, to break them in another order.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub enum TieBreak {
+    /// smaller node index first
+    #[default]
+    Index,
+    /// the given nodes first, then the rest by index, nodes not in the graph are skipped
+    Order(Vec<NodeIndex>),
+    /// a shuffled order, the same seed always gives the same order
+    Seeded(u64),
+}
+
+impl TieBreak {
+    /// nodes ordered by `compare` on their weights, ties by index
+    pub fn by<N, E>(graph: &Graph<N, E>, compare: impl Fn(&N, &N) -> Ordering) -> Self {
+        let mut order = graph.node_indices().collect::<Vec<_>>();
+        order.sort_by(|a, b| compare(&graph[*a], &graph[*b]));
+        TieBreak::Order(order)
+    }
+
+    /// every node index of `graph`, the one that wins a tie first
+    pub fn order<N, E>(&self, graph: &Graph<N, E>) -> Vec<NodeIndex> {
+        match self {
+            TieBreak::Index => graph.node_indices().collect(),
+            TieBreak::Order(given) => {
+                let mut seen = vec![false; graph.node_count()];
+                given
+                    .iter()
+                    .cloned()
+                    .filter(|ni| ni.index() < graph.node_count())
+                    .chain(graph.node_indices())
+                    .filter(|ni| !std::mem::replace(&mut seen[ni.index()], true))
+                    .collect()
+            }
+            TieBreak::Seeded(seed) => {
+                let mut rng = SplitMix64(*seed);
+                let mut order = graph.node_indices().collect::<Vec<_>>();
+                for i in (1..order.len()).rev() {
+                    let j = (rng.next() % (i as u64 + 1)) as usize;
+                    order.swap(i, j);
+                }
+                order
+            }
+        }
+    }
+
+    /// an error if `Order` names nodes that are not in `graph`
+    pub fn validate<N, E>(&self, graph: &Graph<N, E>) -> Result<(), SerializeError> {
+        match self {
+            TieBreak::Order(given) => check_nodes(graph, given),
+            _ => Ok(()),
+        }
+    }
+
+    /// the same tie break on a relabeled graph, see `Acyclifier::feedback_relabeled`
+    fn relabeled<N, E>(&self, graph: &Graph<N, E>, ranks: &[usize]) -> Self {
+        match self {
+            TieBreak::Order(given) => TieBreak::Order(
+                given
+                    .iter()
+                    .filter_map(|ni| relabel(graph, ranks, *ni))
+                    .collect(),
+            ),
+            tie_break => tie_break.clone(),
+        }
+    }
+
+    /// position of every node index in `order`
+    pub fn ranks<N, E>(&self, graph: &Graph<N, E>) -> Vec<usize> {
+        let mut ranks = vec![0; graph.node_count()];
+        for (rank, ni) in self.order(graph).into_iter().enumerate() {
+            ranks[ni.index()] = rank;
+        }
+        ranks
+    }
+}
+
+/// SplitMix64, enough to shuffle ties reproducibly
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// `acyclifier` with its ties broken by `tie_break` instead of by node index
+///
+/// The acyclifier runs on a copy of the graph whose node indices follow `tie_break.order`,
+/// through `Acyclifier::feedback_relabeled` so that nodes it names keep their meaning.
+#[derive(Default, Debug, Clone)]
+pub struct TieBroken<A> {
+    pub acyclifier: A,
+    pub tie_break: TieBreak,
+}
+
+impl<A> TieBroken<A> {
+    /// feedback of `graph`, node `ni` of the graph `tie_break` names is node
+    /// `ranks_in[ni.index()]` of `graph`
+    fn feedback_from<N: Clone, E: Clone>(&self, graph: &Graph<N, E>, ranks_in: &[usize]) -> Feedback
+    where
+        A: Acyclifier<N, E>,
+    {
+        let order = self.tie_break.relabeled(graph, ranks_in).order(graph);
+        let mut ranks = vec![0; graph.node_count()];
+        let mut relabeled = Graph::with_capacity(graph.node_count(), graph.edge_count());
+        for (rank, ni) in order.iter().enumerate() {
+            ranks[ni.index()] = rank;
+            relabeled.add_node(graph[*ni].clone());
+        }
+        // edges are added in the same order, so both graphs share edge indices
+        for e in graph.edge_references() {
+            relabeled.add_edge(
+                NodeIndex::new(ranks[e.source().index()]),
+                NodeIndex::new(ranks[e.target().index()]),
+                e.weight().clone(),
+            );
+        }
+        let ranks = ranks_in
+            .iter()
+            .map(|r| ranks.get(*r).cloned().unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        let mut feedback = self.acyclifier.feedback_relabeled(&relabeled, &ranks);
+        for nodes in feedback.optimal.iter_mut() {
+            for ni in nodes.iter_mut() {
+                *ni = order[ni.index()];
+            }
+            nodes.sort();
+        }
+        if let Status::Partial { unresolved } = &mut feedback.status {
+            for nodes in unresolved.iter_mut() {
+                for ni in nodes.iter_mut() {
+                    *ni = order[ni.index()];
+                }
+                nodes.sort();
+            }
+            unresolved.sort();
+        }
+        feedback
+    }
+}
+
+impl<N: Clone, E: Clone, A: Acyclifier<N, E>> Acyclifier<N, E> for TieBroken<A> {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        let identity = (0..graph.node_count()).collect::<Vec<_>>();
+        self.feedback_from(graph, &identity)
+    }
+
+    fn validate_relabeled(
+        &self,
+        graph: &Graph<N, E>,
+        ranks: &[usize],
+    ) -> Result<(), SerializeError> {
+        if let TieBreak::Order(given) = &self.tie_break {
+            check_relabeled(graph, ranks, given)?;
+        }
+        self.acyclifier.validate_relabeled(graph, ranks)
+    }
+
+    fn feedback_relabeled(&self, graph: &Graph<N, E>, ranks: &[usize]) -> Feedback {
+        self.feedback_from(graph, ranks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::{EdgeIndex, NodeIndex};
+    use petgraph::Graph;
+
+    use super::{TieBreak, TieBroken};
+    use crate::arborescence_acyclifier::ArborescenceAcyclifier;
+    use crate::dfs_acyclifier::{DfsAcyclifier, RootSelection};
+    use crate::eades_acyclifier::EadesAcyclifier;
+    use crate::outgoing_acyclifier::OutGoingAcyclifier;
+    use crate::serialize::{serialize_indexed, serialize_tie_broken, SerializeError};
+    use crate::Acyclifier;
+
+    #[test]
+    fn test_order() {
+        let mut graph = Graph::<&str, ()>::new();
+        for n in ["c", "a", "b"] {
+            graph.add_node(n);
+        }
+        assert_eq!(
+            TieBreak::by(&graph, |a, b| a.cmp(b)).order(&graph),
+            [1, 2, 0].map(NodeIndex::new)
+        );
+        assert_eq!(
+            TieBreak::Order(vec![NodeIndex::new(2)]).order(&graph),
+            [2, 0, 1].map(NodeIndex::new)
+        );
+
+        for _ in 0..17 {
+            graph.add_node("d");
+        }
+        let seeded = TieBreak::Seeded(7).order(&graph);
+        assert_eq!(seeded, TieBreak::Seeded(7).order(&graph));
+        assert_ne!(seeded, TieBreak::Seeded(8).order(&graph));
+        let mut sorted = seeded;
+        sorted.sort();
+        assert_eq!(sorted, graph.node_indices().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_tie_broken() {
+        // nothing tells 0 and 1 apart, so the tie decides which edge goes
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 0)]);
+        assert_eq!(
+            EadesAcyclifier.feedback(&graph).edges,
+            vec![EdgeIndex::new(1)]
+        );
+        let tie_broken = TieBroken {
+            acyclifier: EadesAcyclifier,
+            tie_break: TieBreak::Order(vec![NodeIndex::new(1)]),
+        };
+        assert_eq!(tie_broken.feedback(&graph).edges, vec![EdgeIndex::new(0)]);
+    }
+
+    #[test]
+    fn test_named_nodes_follow_relabeling() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let reversed = TieBreak::Order([2, 1, 0].map(NodeIndex::new).to_vec());
+
+        // starting at 2 the DFS comes back to it over 1 -> 2
+        let dfs = DfsAcyclifier {
+            roots: RootSelection::Given(vec![NodeIndex::new(2)]),
+        };
+        assert_eq!(dfs.feedback(&graph).edges, vec![EdgeIndex::new(1)]);
+        let tie_broken = TieBroken {
+            acyclifier: dfs,
+            tie_break: reversed.clone(),
+        };
+        assert_eq!(tie_broken.feedback(&graph).edges, vec![EdgeIndex::new(1)]);
+
+        // rooted at 0 the arborescence drops the edge into 0
+        let mut arborescence = ArborescenceAcyclifier::new(|_: &()| 1.0);
+        arborescence.root = Some(NodeIndex::new(0));
+        assert_eq!(arborescence.feedback(&graph).edges, vec![EdgeIndex::new(2)]);
+        let tie_broken = TieBroken {
+            acyclifier: arborescence,
+            tie_break: reversed,
+        };
+        assert_eq!(tie_broken.feedback(&graph).edges, vec![EdgeIndex::new(2)]);
+    }
+
+    #[test]
+    fn test_unknown_nodes() {
+        let graph = Graph::<(), ()>::from_edges([(0, 1), (1, 0)]);
+        let unknown = NodeIndex::new(9);
+        assert_eq!(
+            serialize_tie_broken(&graph, &EadesAcyclifier, &TieBreak::Order(vec![unknown])),
+            Err(SerializeError::UnknownNode(unknown))
+        );
+        let dfs = DfsAcyclifier {
+            roots: RootSelection::Given(vec![unknown]),
+        };
+        assert_eq!(dfs.feedback(&graph).edges.len(), 1);
+        assert_eq!(
+            serialize_indexed(&graph, &dfs),
+            Err(SerializeError::UnknownNode(unknown))
+        );
+    }
+
+    #[test]
+    fn test_unresolved_follow_relabeling() {
+        // 1, 2 and 3 all linked both ways next to a lone 0, reversed they are 0, 1 and 2
+        let graph = Graph::<(), ()>::from_edges([(1, 2), (2, 3), (3, 1), (1, 3), (3, 2), (2, 1)]);
+        let limited = OutGoingAcyclifier {
+            limit: Some(0),
+            ..OutGoingAcyclifier::default()
+        };
+        let reversed = TieBreak::Order([3, 2, 1, 0].map(NodeIndex::new).to_vec());
+        assert_eq!(
+            serialize_tie_broken(&graph, &limited, &reversed),
+            Err(SerializeError::Unresolved(vec![[1, 2, 3]
+                .map(NodeIndex::new)
+                .to_vec()]))
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::graphmap::{DiGraphMap, NodeTrait};
+use petgraph::stable_graph::StableGraph;
+use petgraph::visit::{EdgeRef, IntoEdgeReferences, IntoNodeIdentifiers, NodeIndexable};
+use petgraph::Graph;
+
+use crate::mask::Feedback;
+use crate::report::AcyclifyReport;
+use crate::serialize::SerializeError;
+use crate::Acyclifier;
+
+/// a `Graph` copy of any petgraph graph, so that acyclifiers can run on it
+///
+/// Node weights are the ids of the original nodes and edge weights are cloned.
+/// Nodes are added in the order `node_identifiers` yields them.
+#[derive(Debug, Clone)]
+pub struct Compact<Id, EdgeId, E> {
+    pub graph: Graph<Id, E>,
+    /// id of the original edge for every edge index of `graph`
+    pub edge_ids: Vec<EdgeId>,
+    /// index in `graph` of every original node index, `usize::MAX` for holes
+    pub ranks: Vec<usize>,
+}
+
+impl<Id: Copy, EdgeId: Copy, E: Clone> Compact<Id, EdgeId, E> {
+    pub fn new<G>(graph: G) -> Self
+    where
+        G: IntoNodeIdentifiers
+            + IntoEdgeReferences<NodeId = Id, EdgeId = EdgeId, EdgeWeight = E>
+            + NodeIndexable,
+    {
+        let mut compact = Graph::with_capacity(graph.node_bound(), 0);
+        let mut ranks = vec![usize::MAX; graph.node_bound()];
+        for id in graph.node_identifiers() {
+            ranks[graph.to_index(id)] = compact.add_node(id).index();
+        }
+        let mut edge_ids = vec![];
+        for e in graph.edge_references() {
+            compact.add_edge(
+                NodeIndex::new(ranks[graph.to_index(e.source())]),
+                NodeIndex::new(ranks[graph.to_index(e.target())]),
+                e.weight().clone(),
+            );
+            edge_ids.push(e.id());
+        }
+        Compact {
+            graph: compact,
+            edge_ids,
+            ranks,
+        }
+    }
+
+    pub fn edge_id(&self, edge: EdgeIndex) -> EdgeId {
+        self.edge_ids[edge.index()]
+    }
+
+    /// `acyclifier`'s feedback on `graph`, the nodes its configuration names are original
+    /// node indices
+    pub fn feedback<A: Acyclifier<Id, E>>(
+        &self,
+        acyclifier: &A,
+    ) -> Result<Feedback, SerializeError> {
+        acyclifier.validate_relabeled(&self.graph, &self.ranks)?;
+        Ok(acyclifier.feedback_relabeled(&self.graph, &self.ranks))
+    }
+}
+
+/// ids of the edges `acyclifier` wants removed from any petgraph graph
+///
+/// Filter them out with `EdgeFiltered` to get an acyclic view. Nodes named by `acyclifier`
+/// are the indices `NodeIndexable::to_index` gives.
+pub fn feedback_edges<G, A>(graph: G, acyclifier: &A) -> Result<Vec<G::EdgeId>, SerializeError>
+where
+    G: IntoNodeIdentifiers + IntoEdgeReferences + NodeIndexable,
+    G::EdgeWeight: Clone,
+    A: Acyclifier<G::NodeId, G::EdgeWeight>,
+{
+    let compact = Compact::new(graph);
+    Ok(compact
+        .feedback(acyclifier)?
+        .edges
+        .into_iter()
+        .map(|e| compact.edge_id(e))
+        .collect())
+}
+
+/// remove edges from `graph` until it has no cycles, other indices stay valid
+pub fn acyclify_stable_graph<N, E, A>(
+    graph: &mut StableGraph<N, E>,
+    acyclifier: &A,
+) -> Result<AcyclifyReport<E>, SerializeError>
+where
+    E: Clone,
+    A: Acyclifier<NodeIndex, E>,
+{
+    let mut compact = Compact::new(&*graph);
+    let feedback = compact.feedback(acyclifier)?;
+    let edges = feedback
+        .edges
+        .iter()
+        .map(|e| compact.edge_id(*e))
+        .collect::<Vec<_>>();
+    let mut report =
+        AcyclifyReport::apply(&mut compact.graph, feedback).map_nodes(|ni| compact.graph[ni]);
+    report.index_sccs(graph.node_bound());
+    for edge in edges {
+        graph.remove_edge(edge);
+    }
+    Ok(report)
+}
+
+/// remove edges from `graph` until it has no cycles, the report names nodes by their keys
+pub fn acyclify_graph_map<N, E, A>(
+    graph: &mut DiGraphMap<N, E>,
+    acyclifier: &A,
+) -> Result<AcyclifyReport<E, N>, SerializeError>
+where
+    N: NodeTrait,
+    E: Clone,
+    A: Acyclifier<N, E>,
+{
+    let mut compact = Compact::new(&*graph);
+    let feedback = compact.feedback(acyclifier)?;
+    let edges = feedback
+        .edges
+        .iter()
+        .map(|e| compact.edge_id(*e))
+        .collect::<Vec<_>>();
+    let report =
+        AcyclifyReport::apply(&mut compact.graph, feedback).map_nodes(|ni| compact.graph[ni]);
+    for (source, target) in edges {
+        graph.remove_edge(source, target);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::is_cyclic_directed;
+    use petgraph::graph::EdgeIndex;
+    use petgraph::graphmap::DiGraphMap;
+    use petgraph::stable_graph::StableGraph;
+    use petgraph::visit::{EdgeFiltered, EdgeRef};
+
+    use super::{acyclify_graph_map, acyclify_stable_graph, feedback_edges};
+    use crate::arborescence_acyclifier::ArborescenceAcyclifier;
+    use crate::dfs_acyclifier::{DfsAcyclifier, RootSelection};
+    use crate::eades_acyclifier::EadesAcyclifier;
+    use crate::serialize::{serialize_graph, SerializeError};
+
+    #[test]
+    fn test_stable_graph() {
+        let mut graph = StableGraph::<&str, ()>::new();
+        let nis = ["a", "b", "c", "d", "e"].map(|n| graph.add_node(n));
+        graph.add_edge(nis[0], nis[1], ());
+        for (a, b) in [(2, 3), (3, 4), (4, 2)] {
+            graph.add_edge(nis[a], nis[b], ());
+        }
+        // leave a hole so that stable indices and compact indices differ
+        graph.remove_node(nis[1]);
+
+        let report = acyclify_stable_graph(&mut graph, &EadesAcyclifier).unwrap();
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.sccs[0].nodes, vec![nis[2], nis[3], nis[4]]);
+        assert_eq!(report.scc_of(nis[3]), Some(0));
+        assert!(!is_cyclic_directed(&graph));
+        assert_eq!(graph[nis[4]], "e");
+    }
+
+    #[test]
+    fn test_stable_graph_named_nodes() {
+        let mut graph = StableGraph::<&str, f64>::new();
+        let nis = ["a", "b", "c", "d"].map(|n| graph.add_node(n));
+        graph.add_edge(nis[2], nis[3], 1.0);
+        graph.add_edge(nis[3], nis[2], 1.0);
+        // "d" is stable index 3 but compact index 2
+        graph.remove_node(nis[1]);
+
+        // from the root "d" the edge back into it goes
+        let dfs = DfsAcyclifier {
+            roots: RootSelection::Given(vec![nis[3]]),
+        };
+        assert_eq!(feedback_edges(&graph, &dfs), Ok(vec![EdgeIndex::new(0)]));
+        assert_eq!(
+            serialize_graph(&graph, &dfs),
+            Ok(vec![nis[0], nis[3], nis[2]])
+        );
+        let arborescence = ArborescenceAcyclifier {
+            root: Some(nis[3]),
+            ..ArborescenceAcyclifier::new(|w: &f64| *w)
+        };
+        assert_eq!(
+            feedback_edges(&graph, &arborescence),
+            Ok(vec![EdgeIndex::new(0)])
+        );
+
+        // the removed "b" is not in the graph anymore
+        let dfs = DfsAcyclifier {
+            roots: RootSelection::Given(vec![nis[1]]),
+        };
+        assert_eq!(
+            serialize_graph(&graph, &dfs),
+            Err(SerializeError::UnknownNode(nis[1]))
+        );
+        assert_eq!(
+            acyclify_stable_graph(&mut graph, &dfs).map(|report| report.removed.len()),
+            Err(SerializeError::UnknownNode(nis[1]))
+        );
+        assert_eq!(graph.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_graph_map() {
+        let mut graph =
+            DiGraphMap::<&str, ()>::from_edges([("a", "b"), ("b", "c"), ("c", "a"), ("c", "d")]);
+        let removed = feedback_edges(&graph, &EadesAcyclifier).unwrap();
+        let view = EdgeFiltered::from_fn(&graph, |e| !removed.contains(&(e.source(), e.target())));
+        assert!(!is_cyclic_directed(&view));
+
+        let order = serialize_graph(&graph, &EadesAcyclifier).unwrap();
+        assert_eq!(order, vec!["c", "a", "b", "d"]);
+
+        let report = acyclify_graph_map(&mut graph, &EadesAcyclifier).unwrap();
+        assert_eq!(report.sccs[0].nodes, vec!["a", "b", "c"]);
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].scc, Some(0));
+        assert!(!is_cyclic_directed(&graph));
+    }
+}
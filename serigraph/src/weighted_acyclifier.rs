@@ -0,0 +1,427 @@
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, VecDeque};
+
+use crate::mask::Feedback;
+use crate::Acyclifier;
+
+/// largest component solved exactly, the subset table takes 2^n entries
+pub const MAX_EXACT_LIMIT: usize = 24;
+
+/// removes the cheapest set of edges it can find, edge costs are given by `cost`
+///
+/// Strongly connected components with at most `exact_limit` nodes are solved exactly by
+/// dynamic programming over subsets, larger ones with a weighted Eades–Lin–Smyth heuristic.
+/// `exact_limit` is capped at `MAX_EXACT_LIMIT`. Costs are expected to be non-negative.
+pub struct WeightedAcyclifier<F> {
+    pub cost: F,
+    pub exact_limit: usize,
+}
+
+impl<F> WeightedAcyclifier<F> {
+    pub fn new(cost: F) -> Self {
+        WeightedAcyclifier {
+            cost,
+            exact_limit: 16,
+        }
+    }
+}
+
+/// edges of one strongly connected component, with nodes renumbered `0..nodes.len()`
+struct Component {
+    nodes: Vec<NodeIndex>,
+    /// (source, target, cost, edge), self loops excluded
+    edges: Vec<(usize, usize, f64, EdgeIndex)>,
+    self_loops: Vec<EdgeIndex>,
+}
+
+/// strongly connected components with more than one node or with a self loop
+fn cyclic_components<N, E>(graph: &Graph<N, E>, cost: impl Fn(&E) -> f64) -> Vec<Component> {
+    let mut local = vec![(usize::MAX, 0); graph.node_count()];
+    let sccs = tarjan_scc(graph);
+    for (c, scc) in sccs.iter().enumerate() {
+        for (i, ni) in scc.iter().enumerate() {
+            local[ni.index()] = (c, i);
+        }
+    }
+    let mut components = sccs
+        .into_iter()
+        .map(|nodes| Component {
+            nodes,
+            edges: vec![],
+            self_loops: vec![],
+        })
+        .collect::<Vec<_>>();
+    for e in graph.edge_references() {
+        let (cs, s) = local[e.source().index()];
+        let (ct, t) = local[e.target().index()];
+        if cs != ct {
+            continue;
+        }
+        if s == t {
+            components[cs].self_loops.push(e.id());
+        } else {
+            components[cs].edges.push((s, t, cost(e.weight()), e.id()));
+        }
+    }
+    components.retain(|c| c.nodes.len() > 1 || !c.self_loops.is_empty());
+    components
+}
+
+impl Component {
+    /// edges of the component that point backwards in `order`, self loops included
+    fn backward_edges(&self, order: &[usize]) -> Vec<EdgeIndex> {
+        let mut position = vec![0; self.nodes.len()];
+        for (i, v) in order.iter().enumerate() {
+            position[*v] = i;
+        }
+        self.edges
+            .iter()
+            .filter(|(s, t, _, _)| position[*s] > position[*t])
+            .map(|(_, _, _, e)| *e)
+            .chain(self.self_loops.iter().cloned())
+            .collect()
+    }
+}
+
+/// order of `0..n` that minimises the total cost of backward edges, with that cost
+///
+/// Runs in `O(2^n * n)` time and memory, so keep `n` around 20 or below. When every ordering
+/// costs infinity or NaN, the `greedy_ordering` is returned instead.
+pub fn exact_ordering(n: usize, edges: &[(usize, usize, f64)]) -> (Vec<usize>, f64) {
+    if n == 0 {
+        return (vec![], 0.0);
+    }
+    let mut weights = vec![vec![0.0; n]; n];
+    for (s, t, w) in edges {
+        if s != t {
+            weights[*s][*t] += w;
+        }
+    }
+    // cost of putting v after the set S, split into lookup tables for the low and high bits
+    let low_bits = n / 2;
+    let high_bits = n - low_bits;
+    let table = |v: usize, offset: usize, bits: usize| {
+        let mut sums = vec![0.0; 1 << bits];
+        for set in 1..(1usize << bits) {
+            let u = set.trailing_zeros() as usize;
+            sums[set] = sums[set & (set - 1)] + weights[v][u + offset];
+        }
+        sums
+    };
+    let low = (0..n).map(|v| table(v, 0, low_bits)).collect::<Vec<_>>();
+    let high = (0..n)
+        .map(|v| table(v, low_bits, high_bits))
+        .collect::<Vec<_>>();
+    let low_mask = (1 << low_bits) - 1;
+    let after = |v: usize, set: usize| low[v][set & low_mask] + high[v][set >> low_bits];
+
+    let full = (1usize << n) - 1;
+    let mut best = vec![f64::INFINITY; full + 1];
+    // the node added last to reach a set, `u8::MAX` where no ordering reached it
+    let mut last = vec![u8::MAX; full + 1];
+    best[0] = 0.0;
+    for set in 0..full {
+        if best[set] == f64::INFINITY {
+            continue;
+        }
+        for v in 0..n {
+            if set & (1 << v) != 0 {
+                continue;
+            }
+            let next = set | (1 << v);
+            let cost = best[set] + after(v, set);
+            if cost < best[next] {
+                best[next] = cost;
+                last[next] = v as u8;
+            }
+        }
+    }
+
+    // infinite or NaN costs everywhere leave the full set unreached
+    if last[full] == u8::MAX {
+        return (greedy_ordering(n, edges), best[full]);
+    }
+    let mut order = Vec::with_capacity(n);
+    let mut set = full;
+    while set != 0 {
+        let v = last[set] as usize;
+        order.push(v);
+        set &= !(1 << v);
+    }
+    order.reverse();
+    (order, best[full])
+}
+
+/// an `f64` ordered and compared by `total_cmp`, so NaN costs stay comparable
+struct Key(f64);
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+fn pop_valid(queue: &mut VecDeque<usize>, valid: impl Fn(usize) -> bool) -> Option<usize> {
+    while let Some(v) = queue.pop_front() {
+        if valid(v) {
+            return Some(v);
+        }
+    }
+    None
+}
+
+/// weighted Eades–Lin–Smyth ordering of `0..n`
+///
+/// Like `eades_acyclifier::eades_ordering` but the node moved to the front is the one with
+/// the largest difference between outgoing and incoming edge cost.
+pub fn greedy_ordering(n: usize, edges: &[(usize, usize, f64)]) -> Vec<usize> {
+    let mut outs = vec![vec![]; n];
+    let mut ins = vec![vec![]; n];
+    let mut out_count = vec![0; n];
+    let mut in_count = vec![0; n];
+    let mut delta = vec![0.0; n];
+    for (s, t, w) in edges {
+        if s == t {
+            continue;
+        }
+        outs[*s].push((*t, *w));
+        ins[*t].push((*s, *w));
+        out_count[*s] += 1;
+        in_count[*t] += 1;
+        delta[*s] += w;
+        delta[*t] -= w;
+    }
+
+    let mut removed = vec![false; n];
+    let mut sinks = (0..n)
+        .filter(|v| out_count[*v] == 0)
+        .collect::<VecDeque<_>>();
+    let mut sources = (0..n)
+        .filter(|v| in_count[*v] == 0)
+        .collect::<VecDeque<_>>();
+    let mut heap = (0..n)
+        .map(|v| (Key(delta[v]), Reverse(v)))
+        .collect::<BinaryHeap<_>>();
+    let mut head = Vec::with_capacity(n);
+    let mut tail = vec![];
+    for _ in 0..n {
+        let v = if let Some(v) = pop_valid(&mut sinks, |v| !removed[v]) {
+            tail.push(v);
+            v
+        } else if let Some(v) = pop_valid(&mut sources, |v| !removed[v]) {
+            head.push(v);
+            v
+        } else {
+            let v = loop {
+                let (key, Reverse(v)) = heap.pop().expect("a node must remain");
+                if !removed[v] && key == Key(delta[v]) {
+                    break v;
+                }
+            };
+            head.push(v);
+            v
+        };
+        removed[v] = true;
+        for (u, w) in &ins[v] {
+            if !removed[*u] {
+                out_count[*u] -= 1;
+                delta[*u] -= w;
+                heap.push((Key(delta[*u]), Reverse(*u)));
+                if out_count[*u] == 0 {
+                    sinks.push_back(*u);
+                }
+            }
+        }
+        for (u, w) in &outs[v] {
+            if !removed[*u] {
+                in_count[*u] -= 1;
+                delta[*u] += w;
+                heap.push((Key(delta[*u]), Reverse(*u)));
+                if in_count[*u] == 0 {
+                    sources.push_back(*u);
+                }
+            }
+        }
+    }
+    head.extend(tail.into_iter().rev());
+    head
+}
+
+/// how one strongly connected component was broken
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SccSolution {
+    pub nodes: Vec<NodeIndex>,
+    pub removed: Vec<EdgeIndex>,
+    /// whether `removed` is a minimum feedback arc set of the component
+    pub optimal: bool,
+}
+
+/// break every cyclic component exactly up to `exact_limit` nodes, at most
+/// `MAX_EXACT_LIMIT`, greedily above
+pub(crate) fn solve_components<N, E>(
+    graph: &Graph<N, E>,
+    cost: impl Fn(&E) -> f64,
+    exact_limit: usize,
+) -> Vec<SccSolution> {
+    cyclic_components(graph, cost)
+        .into_iter()
+        .map(|component| {
+            let n = component.nodes.len();
+            let edges = component
+                .edges
+                .iter()
+                .map(|(s, t, w, _)| (*s, *t, *w))
+                .collect::<Vec<_>>();
+            let optimal = n <= exact_limit.min(MAX_EXACT_LIMIT);
+            let order = if optimal {
+                exact_ordering(n, &edges).0
+            } else {
+                greedy_ordering(n, &edges)
+            };
+            let removed = component.backward_edges(&order);
+            SccSolution {
+                nodes: component.nodes,
+                removed,
+                optimal,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn into_feedback(solutions: Vec<SccSolution>) -> Feedback {
+    let mut feedback = Feedback {
+        iterations: 1,
+        ..Feedback::default()
+    };
+    for solution in solutions {
+        feedback.edges.extend(solution.removed);
+        if solution.optimal {
+            feedback.optimal.push(solution.nodes);
+        }
+    }
+    feedback
+}
+
+impl<F> WeightedAcyclifier<F> {
+    pub fn solve<N, E>(&self, graph: &Graph<N, E>) -> Vec<SccSolution>
+    where
+        F: Fn(&E) -> f64,
+    {
+        solve_components(graph, &self.cost, self.exact_limit)
+    }
+}
+
+impl<N, E, F: Fn(&E) -> f64> Acyclifier<N, E> for WeightedAcyclifier<F> {
+    fn feedback(&self, graph: &Graph<N, E>) -> Feedback {
+        into_feedback(self.solve(graph))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::algo::is_cyclic_directed;
+    use petgraph::graph::NodeIndex;
+    use petgraph::Graph;
+
+    use super::{exact_ordering, greedy_ordering, WeightedAcyclifier, MAX_EXACT_LIMIT};
+    use crate::Acyclifier;
+
+    #[test]
+    fn test_exact_ordering() {
+        // 0 -> 1 -> 2 -> 0 with a cheap 2 -> 0, and an expensive 1 -> 0 against a cheap 0 -> 1
+        let edges = [(0, 1, 1.0), (1, 2, 3.0), (2, 0, 0.5), (1, 0, 4.0)];
+        let (order, cost) = exact_ordering(3, &edges);
+        assert_eq!(order, vec![1, 2, 0]);
+        assert_eq!(cost, 1.0);
+    }
+
+    #[test]
+    fn test_greedy_ordering() {
+        let edges = [(0, 1, 1.0), (1, 2, 3.0), (2, 0, 0.5), (1, 0, 4.0)];
+        assert_eq!(greedy_ordering(3, &edges), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_acyclify() {
+        let mut graph = Graph::<i32, f64>::new();
+        let nis = (0..5).map(|i| graph.add_node(i)).collect::<Vec<_>>();
+        for (a, b, w) in [
+            (0, 1, 5.0),
+            (1, 2, 5.0),
+            (2, 0, 1.0),
+            (2, 3, 5.0),
+            (3, 4, 5.0),
+            (4, 2, 2.0),
+            (4, 4, 9.0),
+        ] {
+            graph.add_edge(nis[a], nis[b], w);
+        }
+        for exact_limit in [0, 16] {
+            let mut g = graph.clone();
+            let acy = WeightedAcyclifier {
+                cost: |w: &f64| *w,
+                exact_limit,
+            };
+            acy.acyclify(&mut g);
+            assert!(!is_cyclic_directed(&g));
+            assert_eq!(g.edge_count(), 4);
+            assert!(g.find_edge(NodeIndex::new(2), NodeIndex::new(0)).is_none());
+            assert!(g.find_edge(NodeIndex::new(4), NodeIndex::new(2)).is_none());
+        }
+    }
+
+    #[test]
+    fn test_exact_limit_capped() {
+        // one cycle through every node, too large for the subset table
+        let n = MAX_EXACT_LIMIT + 1;
+        let graph =
+            Graph::<(), f64>::from_edges((0..n).map(|i| (i as u32, ((i + 1) % n) as u32, 1.0)));
+        let acy = WeightedAcyclifier {
+            cost: |w: &f64| *w,
+            exact_limit: usize::MAX,
+        };
+        let solutions = acy.solve(&graph);
+        assert_eq!(solutions.len(), 1);
+        assert!(!solutions[0].optimal);
+        assert_eq!(solutions[0].removed.len(), 1);
+    }
+
+    #[test]
+    fn test_infinite_costs() {
+        // every edge of a complete digraph is "never cut", yet some must go
+        let edges =
+            [(0, 1), (1, 0), (0, 2), (2, 0), (1, 2), (2, 1)].map(|(s, t)| (s, t, f64::INFINITY));
+        let (mut order, cost) = exact_ordering(3, &edges);
+        assert_eq!(cost, f64::INFINITY);
+        order.sort_unstable();
+        assert_eq!(order, vec![0, 1, 2]);
+
+        let mut graph =
+            Graph::<(), f64>::from_edges(edges.map(|(s, t, w)| (s as u32, t as u32, w)));
+        let acy = WeightedAcyclifier {
+            cost: |w: &f64| *w,
+            exact_limit: 16,
+        };
+        acy.acyclify(&mut graph);
+        assert!(!is_cyclic_directed(&graph));
+        assert_eq!(graph.edge_count(), 3);
+    }
+}
@@ -89,5 +89,21 @@ edges = [
     (4, 5, 1),
     (5, 2, 2),
 ]
-res = solve(n, edges, 0)
+# python chu_liu.py graph.json [root]
+# graph.json: serigraph::io::write_node_link の出力 (networkx の node-link 形式)
+def load_node_link(path):
+    import json
+    with open(path) as f:
+        data = json.load(f)
+    ids = {node['id']: i for i, node in enumerate(data['nodes'])}
+    links = data.get('links', data.get('edges', []))
+    return len(ids), [(ids[l['source']], ids[l['target']], l.get('weight', 1)) for l in links]
+
+import sys
+r = 0
+if len(sys.argv) > 1:
+    n, edges = load_node_link(sys.argv[1])
+    r = int(sys.argv[2]) if len(sys.argv) > 2 else 0
+
+res = solve(n, edges, r)
 print(res)
\ No newline at end of file